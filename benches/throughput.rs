@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+
+const CHUNK_SIZES: [usize; 4] = [64, 4 * 1024, 64 * 1024, 1024 * 1024];
+const INPUT_SIZE: usize = 4 * 1024 * 1024;
+
+fn input() -> Vec<u8> {
+    (0..INPUT_SIZE).map(|i| (i * 131 + 7) as u8).collect()
+}
+
+fn encode(c: &mut Criterion) {
+    let data = input();
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for chunk_size in CHUNK_SIZES {
+        group.bench_with_input(BenchmarkId::new("update", chunk_size), &chunk_size, |b, &chunk_size| {
+            b.iter(|| {
+                let mut encoder = BaseHanEncoder::with_buffer_size(chunk_size);
+                for chunk in data.chunks(chunk_size) {
+                    black_box(encoder.update(chunk).unwrap());
+                }
+                black_box(encoder.finish().unwrap())
+            })
+        });
+    }
+    group.bench_function("encode_bytes_to_string", |b| {
+        b.iter(|| BaseHanEncoder::encode_bytes_to_string(black_box(&data)).unwrap())
+    });
+    group.bench_function("v0", |b| b.iter(|| basehan::encode(black_box(&data)).unwrap()));
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let data = input();
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(&data).unwrap().chars().collect();
+    let encoded_str = String::from_iter(&encoded);
+    let v0_encoded = basehan::encode(&data).unwrap();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for chunk_size in CHUNK_SIZES {
+        // Chunks of chars decoding to about `chunk_size` bytes
+        let nchars = (chunk_size * 8 / 13).max(1);
+        group.bench_with_input(BenchmarkId::new("update", chunk_size), &nchars, |b, &nchars| {
+            b.iter(|| {
+                let mut decoder = BaseHanDecoder::with_buffer_size(chunk_size);
+                for chunk in encoded.chunks(nchars) {
+                    black_box(decoder.update(chunk).unwrap());
+                }
+                black_box(decoder.finish().unwrap())
+            })
+        });
+    }
+    group.bench_function("decode_str_to_bytes", |b| {
+        b.iter(|| BaseHanDecoder::decode_str_to_bytes(black_box(&encoded_str)).unwrap())
+    });
+    group.bench_function("v0", |b| b.iter(|| basehan::decode(black_box(&v0_encoded)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
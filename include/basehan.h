@@ -0,0 +1,53 @@
+#ifndef BASEHAN_H
+#define BASEHAN_H
+
+/* Generated by cbindgen, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define BASEHAN_OK 0
+
+/**
+ * A required pointer is null.
+ */
+#define BASEHAN_NULL_POINTER -1
+
+/**
+ * The output buffer is smaller than `*out_len` on return.
+ */
+#define BASEHAN_BUFFER_TOO_SMALL -2
+
+/**
+ * The input of `basehan_decode` is not UTF-8.
+ */
+#define BASEHAN_INVALID_UTF8 -3
+
+/**
+ * The input of `basehan_decode` is not valid Base-Han.
+ */
+#define BASEHAN_INVALID_INPUT -4
+
+/**
+ * Encode `len` bytes at `input` to UTF-8 Base-Han text at `out`, which is not NUL-terminated.
+ *
+ * # Safety
+ *
+ * `input` must be valid for reads of `len` bytes, unless `len` is 0. `out_len` must be a valid
+ * pointer, and `out` must be valid for writes of `*out_len` bytes, unless `*out_len` is 0.
+ */
+int32_t basehan_encode(const uint8_t *input, size_t len, uint8_t *out, size_t *out_len);
+
+/**
+ * Decode `len` bytes of UTF-8 Base-Han text at `input` to bytes at `out`.
+ *
+ * # Safety
+ *
+ * Same as [`basehan_encode`].
+ */
+int32_t basehan_decode(const uint8_t *input, size_t len, uint8_t *out, size_t *out_len);
+
+#endif  /* BASEHAN_H */
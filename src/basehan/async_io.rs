@@ -0,0 +1,208 @@
+//! Tokio adapters for the v1 streaming codec.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::basehan::v1::{
+    into_io_error, take_utf8_chars, truncated_stream, BaseHanDecoder, BaseHanEncoder, BaseHanError,
+};
+
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Encode bytes written to it and forward the Base-Han text (UTF-8) to `inner`.
+/// The terminator is written on `shutdown`.
+pub struct AsyncBaseHanEncoder<W> {
+    inner: W,
+    encoder: BaseHanEncoder,
+    buf_out: Vec<u8>, // UTF-8 not yet accepted by inner
+    pos: usize,
+    finished: bool,
+}
+
+impl<W> AsyncBaseHanEncoder<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(inner: W) -> Self {
+        AsyncBaseHanEncoder::with_encoder(inner, BaseHanEncoder::new())
+    }
+
+    /// Use a configured encoder, e.g. `BaseHanEncoder::with_offset`.
+    pub fn with_encoder(inner: W, encoder: BaseHanEncoder) -> Self {
+        AsyncBaseHanEncoder {
+            inner,
+            encoder,
+            buf_out: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Write the buffered output to inner, pending until inner accepts all of it.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pos < self.buf_out.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.buf_out[self.pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pos += n;
+        }
+        self.buf_out.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    fn push_chars<I>(&mut self, chars: I)
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut utf8 = [0u8; 4];
+        for c in chars {
+            self.buf_out.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        }
+    }
+}
+
+impl<W> AsyncWrite for AsyncBaseHanEncoder<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // Accept no more input until the previous output is drained
+        ready!(this.poll_drain(cx))?;
+        let chars = this.encoder.update(buf).map_err(into_io_error)?;
+        this.push_chars(chars);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.finished {
+            let terminator = this.encoder.finish_in_place();
+            this.push_chars(terminator);
+            this.finished = true;
+        }
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Read Base-Han text (UTF-8) from `inner` and yield the decoded bytes.
+pub struct AsyncBaseHanDecoder<R> {
+    inner: R,
+    decoder: BaseHanDecoder,
+    buf_in: Vec<u8>,
+    pending: Vec<u8>, // Bytes of a char split by the chunk boundary
+    buf_out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R> AsyncBaseHanDecoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    pub fn new(inner: R) -> Self {
+        AsyncBaseHanDecoder::with_decoder(inner, BaseHanDecoder::new())
+    }
+
+    /// Use a configured decoder, e.g. `BaseHanDecoder::with_offset`.
+    pub fn with_decoder(inner: R, decoder: BaseHanDecoder) -> Self {
+        AsyncBaseHanDecoder {
+            inner,
+            decoder,
+            buf_in: vec![0u8; READ_CHUNK_SIZE],
+            pending: Vec::new(),
+            buf_out: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> AsyncRead for AsyncBaseHanDecoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // Decode another chunk only when all decoded bytes have been handed out
+        while this.pos == this.buf_out.len() {
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+            let mut read_buf = ReadBuf::new(&mut this.buf_in);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            let filled = read_buf.filled();
+            if filled.is_empty() {
+                this.done = true;
+                if !this.pending.is_empty() {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the string input is corrupted",
+                    )));
+                }
+                // A stream is not over until its terminator, unless empty
+                if this.decoder.chars_consumed() > 0 {
+                    return Poll::Ready(Err(truncated_stream()));
+                }
+                continue;
+            }
+            this.pending.extend_from_slice(filled);
+            let chars = take_utf8_chars(&mut this.pending).map_err(into_io_error)?;
+            this.buf_out = this.decoder.update(chars).map_err(into_io_error)?;
+            this.pos = 0;
+            // Anything after the terminator is not ours
+            this.done = this.decoder.is_finished();
+        }
+        let n = buf.remaining().min(this.buf_out.len() - this.pos);
+        buf.put_slice(&this.buf_out[this.pos..this.pos + n]);
+        this.pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Encode everything from `reader` into `writer`, terminator included, then shut `writer` down.
+/// Return the number of bytes read.
+pub async fn encode_async<R, W>(reader: &mut R, writer: W) -> Result<u64, BaseHanError>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin,
+{
+    let mut encoder = AsyncBaseHanEncoder::new(writer);
+    let total = tokio::io::copy(reader, &mut encoder).await.map_err(BaseHanError::IoError)?;
+    encoder.shutdown().await.map_err(BaseHanError::IoError)?;
+    Ok(total)
+}
+
+/// Decode Base-Han text from `reader` into `writer`, until the terminator.
+/// Return the number of bytes written, or an `UnexpectedEof` IO error if `reader` ends before
+/// the terminator.
+pub async fn decode_async<R, W>(reader: R, writer: &mut W) -> Result<u64, BaseHanError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut decoder = AsyncBaseHanDecoder::new(reader);
+    let total = tokio::io::copy(&mut decoder, writer).await.map_err(BaseHanError::IoError)?;
+    writer.flush().await.map_err(BaseHanError::IoError)?;
+    Ok(total)
+}
@@ -0,0 +1,90 @@
+//! A Base64 look-alike packing 6 bits per char, 3 bytes into 4 chars.
+//!
+//! Base64 index `i` maps to `BASE64_OFFSET + i` and the `=` padding to `BASE64_PAD`,
+//! so a Base64 string converts to this encoding (and back) char by char,
+//! and the output length is the same as Base64's, about twice as long as v1's.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::basehan::v1::{is_skipped, BaseHanError};
+
+/// The char of Base64 index 0 (`A`), the alphabet is the 64 chars from there.
+pub const BASE64_OFFSET: u32 = 0x4e00;
+/// Stands for Base64's `=` padding, right after the alphabet.
+pub const BASE64_PAD: char = '\u{4e40}';
+
+fn code(value: u8) -> char {
+    char::from_u32(BASE64_OFFSET + value as u32)
+        .expect("Data cannot convert to a valid char, which should never happen.")
+}
+
+/// Encode `input` 3 bytes to 4 chars, padding the last group with `BASE64_PAD` like Base64 does.
+pub fn encode_base64_compat<T: AsRef<[u8]>>(input: T) -> String {
+    let input = input.as_ref();
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4 * 3); // 3 UTF-8 bytes per char
+    for group in input.chunks(3) {
+        let bits = group.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(code((bits >> (18 - 6 * i) & 0x3f) as u8));
+            } else {
+                out.push(BASE64_PAD);
+            }
+        }
+    }
+    out
+}
+
+/// Decode the output of `encode_base64_compat`, ignoring line wrapping.
+/// Return `TruncatedStream` if the chars do not make whole groups of 4,
+/// `InvalidCode` for misplaced padding and `CodeOutOfRange` for chars out of the alphabet.
+pub fn decode_base64_compat(input: &str) -> Result<Vec<u8>, BaseHanError> {
+    let mut out = Vec::with_capacity(input.len() / 3 / 4 * 3); // 3 UTF-8 bytes per char
+    let mut bits = 0u32;
+    let mut ndata = 0; // Data chars in the current group
+    let mut npad = 0; // Padding chars in the current group
+    let mut ended = false;
+    for (i, c) in input.chars().enumerate() {
+        if is_skipped(c) {
+            continue;
+        }
+        let code = c as u32;
+        if ended {
+            return Err(BaseHanError::InvalidCode(code, i));
+        }
+        if c == BASE64_PAD {
+            // Only the last 1 or 2 chars of the last group, behind at least 2 data chars
+            if ndata + npad < 2 {
+                return Err(BaseHanError::InvalidCode(code, i));
+            }
+            npad += 1;
+        } else if (BASE64_OFFSET..BASE64_OFFSET + 64).contains(&code) {
+            if npad > 0 {
+                return Err(BaseHanError::InvalidCode(code, i));
+            }
+            bits = bits << 6 | (code - BASE64_OFFSET);
+            ndata += 1;
+        } else {
+            return Err(BaseHanError::CodeOutOfRange {
+                code,
+                pos: i,
+                valid_min: BASE64_OFFSET,
+                valid_max: BASE64_PAD as u32,
+            });
+        }
+        if ndata + npad == 4 {
+            // 4 data chars are 3 bytes, each padding char drops one of them
+            let bytes = (bits << (6 * npad)).to_be_bytes();
+            out.extend_from_slice(&bytes[1..4 - npad]);
+            ended = npad > 0;
+            bits = 0;
+            ndata = 0;
+            npad = 0;
+        }
+    }
+    if ndata + npad != 0 {
+        return Err(BaseHanError::TruncatedStream);
+    }
+    Ok(out)
+}
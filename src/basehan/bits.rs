@@ -0,0 +1,156 @@
+//! Bit packing between bytes and 13-bit groups, the building blocks of the v1 streaming codec.
+//!
+//! These know nothing about code points, so they can be used to map 13-bit groups
+//! to any Unicode block.
+
+use crate::basehan::bits::BitCache8Out::{Double, Single};
+
+/// Collects bytes and pops 13-bit groups.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BitCache13 {
+    inner: u32,
+    nbits: usize,
+}
+
+impl BitCache13 {
+    /// Start with `nbits` bits already cached, taken from the low bits of `initial_inner`.
+    ///
+    /// # Panics
+    /// If `nbits` is not below 13.
+    pub fn new(initial_inner: u32, nbits: usize) -> Self {
+        assert!(nbits < 13, "BitCache13 holds at most 12 bits, got {}", nbits);
+        BitCache13 {
+            inner: initial_inner & ((1 << nbits) - 1),
+            nbits,
+        }
+    }
+
+    /// Number of bits cached, always below 13.
+    pub fn nbits(&self) -> usize {
+        self.nbits
+    }
+
+    /// Fill one byte at a time, if full(13 bits), return the 13-bit group and pop it.
+    /// Otherwise, return none.
+    pub fn fill(&mut self, byte: u8) -> Option<u32> {
+        let remain_bits = (self.nbits + 8) % 13;
+        let out = match self.nbits {
+            0..=4 => { // Not full, return none
+                self.inner <<= 8;
+                self.inner |= byte as u32;
+                None
+            }
+            5..=12 => {
+                self.inner <<= 8;
+                self.inner |= byte as u32;
+                // Keep the 13-bit window only, e.g. all of `inner` when `nbits` was 5 (no shift)
+                let output_u32 = (self.inner >> remain_bits) & 0x1FFF;
+                self.inner &= (1 << remain_bits) - 1; // head padding nums overflows in u8, and then appended to the buffer
+                Some(output_u32)
+            }
+            13.. =>
+                panic!("Remaining bits overflow! This should never happen!")
+        };
+        self.nbits = (self.nbits + 8) % 13;
+        out
+    }
+
+    /// Dump the remaining bits, aligned right.
+    /// Take `nbits` beforehand: 1 can refer to 1 or 01 or 001 and so on.
+    pub fn dump(self) -> u32 {
+        self.inner
+    }
+}
+
+/// Output of `BitCache8::fill`, 13 bits complete either one or two bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitCache8Out {
+    Single(u8),
+    Double([u8; 2]),
+}
+
+/// Collects 13-bit groups and pops bytes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BitCache8 {
+    inner: u32,
+    nbits: usize,
+}
+
+impl BitCache8 {
+    /// Start with `nbits` bits already cached, taken from the low bits of `initial_inner`.
+    ///
+    /// # Panics
+    /// If `nbits` is not below 8.
+    pub fn new(initial_inner: u32, nbits: usize) -> Self {
+        assert!(nbits < 8, "BitCache8 holds at most 7 bits, got {}", nbits);
+        BitCache8 {
+            inner: initial_inner & ((1 << nbits) - 1),
+            nbits,
+        }
+    }
+
+    /// Number of bits cached, always below 8.
+    pub fn nbits(&self) -> usize {
+        self.nbits
+    }
+
+    /// Fill 13 bits at a time. The remaining bits are left-aligned (the same as BitCache13)
+    /// Return one byte or 2 bytes
+    pub fn fill(&mut self, bits: u32) -> BitCache8Out {
+        let remain_bits = (self.nbits + 13) % 8;
+        let out = match self.nbits {
+            0..=2 => {
+                self.inner <<= 13;
+                self.inner |= bits;
+                let out_byte = (self.inner >> remain_bits) as u8;
+                self.inner &= (1 << remain_bits) - 1;
+                Single(out_byte)
+            }
+            3.. => {
+                self.inner <<= 13;
+                self.inner |= bits;
+                let out_byte_1 = (self.inner >> (remain_bits + 8)) as u8;
+                let out_byte_2 =(self.inner >> remain_bits) as u8;
+                self.inner &= (1 << remain_bits) - 1;
+                Double([out_byte_1, out_byte_2])
+            }
+        };
+        self.nbits = (self.nbits + 13) % 8;
+        out
+    }
+
+    /// Fill the last `nbits` (at most 13) bits of a stream, which may complete no byte at all.
+    ///
+    /// # Panics
+    /// If `nbits` is above 13.
+    pub fn fill_tail(&mut self, bits: u32, nbits: usize) -> Option<BitCache8Out> {
+        assert!(nbits <= 13, "BitCache8 takes at most 13 bits at a time, got {}", nbits);
+        self.inner <<= nbits;
+        self.inner |= bits & ((1 << nbits) - 1);
+        self.nbits += nbits;
+        let out = match self.nbits {
+            0..=7 => None,
+            8..=15 => {
+                self.nbits -= 8;
+                Some(Single((self.inner >> self.nbits) as u8))
+            }
+            _ => {
+                self.nbits -= 16;
+                let out_byte_1 = (self.inner >> (self.nbits + 8)) as u8;
+                let out_byte_2 = (self.inner >> self.nbits) as u8;
+                Some(Double([out_byte_1, out_byte_2]))
+            }
+        };
+        self.inner &= (1 << self.nbits) - 1;
+        out
+    }
+
+    /// Dump the remaining byte out.
+    /// Typically, this is expected to return none when reaching the last character.
+    pub fn dump(self) -> Option<u8> {
+        if self.inner != 0 {
+            return Some(self.inner as u8);
+        }
+        None
+    }
+}
@@ -0,0 +1,95 @@
+//! C ABI of the v1 codec.
+//!
+//! Build a library with `cargo rustc --release --lib --features ffi --crate-type cdylib`
+//! (or `staticlib`), and generate `basehan.h` with `make header`, which runs `cbindgen`.
+//!
+//! Both functions write into a caller-owned buffer: `*out_len` holds its capacity on entry and
+//! the length of the output on return. When the buffer is too small, nothing is written,
+//! `BASEHAN_BUFFER_TOO_SMALL` is returned and `*out_len` is set to the required length, so
+//! calling with `out == NULL` and `*out_len == 0` queries the size.
+
+use core::{ptr, slice, str};
+
+use crate::basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+
+pub const BASEHAN_OK: i32 = 0;
+/// A required pointer is null.
+pub const BASEHAN_NULL_POINTER: i32 = -1;
+/// The output buffer is smaller than `*out_len` on return.
+pub const BASEHAN_BUFFER_TOO_SMALL: i32 = -2;
+/// The input of `basehan_decode` is not UTF-8.
+pub const BASEHAN_INVALID_UTF8: i32 = -3;
+/// The input of `basehan_decode` is not valid Base-Han.
+pub const BASEHAN_INVALID_INPUT: i32 = -4;
+
+/// Encode `len` bytes at `input` to UTF-8 Base-Han text at `out`, which is not NUL-terminated.
+///
+/// # Safety
+///
+/// `input` must be valid for reads of `len` bytes, unless `len` is 0. `out_len` must be a valid
+/// pointer, and `out` must be valid for writes of `*out_len` bytes, unless `*out_len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn basehan_encode(
+    input: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    let Some(input) = input_slice(input, len) else {
+        return BASEHAN_NULL_POINTER;
+    };
+    match BaseHanEncoder::encode_bytes_to_string(input) {
+        Ok(encoded) => write_out(encoded.as_bytes(), out, out_len),
+        Err(_) => BASEHAN_INVALID_INPUT, // Encoding in memory never fails
+    }
+}
+
+/// Decode `len` bytes of UTF-8 Base-Han text at `input` to bytes at `out`.
+///
+/// # Safety
+///
+/// Same as [`basehan_encode`].
+#[no_mangle]
+pub unsafe extern "C" fn basehan_decode(
+    input: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    let Some(input) = input_slice(input, len) else {
+        return BASEHAN_NULL_POINTER;
+    };
+    let Ok(input) = str::from_utf8(input) else {
+        return BASEHAN_INVALID_UTF8;
+    };
+    match BaseHanDecoder::decode_str_to_bytes(input) {
+        Ok(decoded) => write_out(&decoded, out, out_len),
+        Err(_) => BASEHAN_INVALID_INPUT,
+    }
+}
+
+unsafe fn input_slice<'a>(input: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (input.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(input, len)),
+    }
+}
+
+unsafe fn write_out(bytes: &[u8], out: *mut u8, out_len: *mut usize) -> i32 {
+    if out_len.is_null() {
+        return BASEHAN_NULL_POINTER;
+    }
+    let capacity = *out_len;
+    *out_len = bytes.len();
+    if bytes.len() > capacity {
+        return BASEHAN_BUFFER_TOO_SMALL;
+    }
+    if !bytes.is_empty() {
+        if out.is_null() {
+            return BASEHAN_NULL_POINTER;
+        }
+        ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    }
+    BASEHAN_OK
+}
@@ -0,0 +1,74 @@
+//! HMAC-SHA256 tags appended after the v1 terminator, see `BaseHanEncoder::with_hmac`.
+
+use alloc::vec::Vec;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::basehan::bits::BitCache8;
+use crate::basehan::bits::BitCache8Out::{Double, Single};
+
+pub(crate) type HmacSha256 = Hmac<Sha256>;
+
+/// The MAC is truncated to 13 bytes, which make exactly 8 chars of 13 bits.
+pub(crate) const TAG_LEN: usize = 13;
+pub(crate) const TAG_CHARS: usize = 8;
+
+pub(crate) fn new_mac(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length")
+}
+
+/// Finalize the truncated tag, leaving `mac` ready for another stream with the same key.
+pub(crate) fn take_tag(mac: &mut HmacSha256) -> [u8; TAG_LEN] {
+    let full = mac.finalize_reset().into_bytes();
+    let mut tag = [0u8; TAG_LEN];
+    tag.copy_from_slice(&full[..TAG_LEN]);
+    tag
+}
+
+/// Collects the tag chars following the terminator and checks them against the decoded bytes.
+#[derive(Clone)]
+pub(crate) struct Verifier {
+    mac: HmacSha256,
+    tag: Vec<u8>,
+    cache: BitCache8,
+}
+
+impl Verifier {
+    pub(crate) fn new(key: &[u8]) -> Self {
+        Verifier {
+            mac: new_mac(key),
+            tag: Vec::with_capacity(TAG_LEN),
+            cache: BitCache8::default(),
+        }
+    }
+
+    /// Forget the current stream, keeping the key.
+    pub(crate) fn reset(&mut self) {
+        Mac::reset(&mut self.mac);
+        self.tag.clear();
+        self.cache = BitCache8::default();
+    }
+
+    pub(crate) fn update(&mut self, decoded: &[u8]) {
+        self.mac.update(decoded);
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.tag.len() == TAG_LEN
+    }
+
+    /// Feed the 13 bits of a tag char, return whether the tag is complete.
+    pub(crate) fn push(&mut self, index: u32) -> bool {
+        match self.cache.fill(index) {
+            Single(byte) => self.tag.push(byte),
+            Double(bytes) => self.tag.extend_from_slice(&bytes),
+        }
+        self.is_complete()
+    }
+
+    /// Compare the complete tag with the MAC of the decoded bytes, in constant time.
+    pub(crate) fn verify(&self) -> bool {
+        self.mac.clone().verify_truncated_left(&self.tag).is_ok()
+    }
+}
@@ -1,9 +1,38 @@
 pub mod v1;
+pub mod v2;
+pub mod bits;
+mod base64_compat;
+pub use base64_compat::{decode_base64_compat, encode_base64_compat, BASE64_OFFSET, BASE64_PAD};
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+pub mod pipeline;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "hmac")]
+mod integrity;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "rayon")]
+pub use parallel::parallel_encode;
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use v1::{decode_to_os_string, encode_os_str};
+pub use v1::decode_lossy;
 
-use std::collections::VecDeque;
-use std::io::Write;
+use alloc::{format, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
 
-const BASE_OFFSET: u32 = 0x4e00;
+pub(crate) const BASE_OFFSET: u32 = 0x4e00;
 const CODE_RANGE: u32 = 0x1FFF;
 const MULTIBYTE_SIGN: u32 = 0x8e00;
 
@@ -12,6 +41,64 @@ const MULTIBYTE_SIGN: u32 = 0x8e00;
 pub enum BaseHanError {
     InternalError(String),
     InvalidCode(u32, usize),
+    #[cfg(feature = "std")]
+    IoError(io::Error),
+}
+
+// `io::Error` is neither `Clone` nor `PartialEq`, IO errors are compared and cloned by kind & message.
+impl Clone for BaseHanError {
+    fn clone(&self) -> Self {
+        match self {
+            BaseHanError::InternalError(msg) => BaseHanError::InternalError(msg.clone()),
+            BaseHanError::InvalidCode(code, pos) => BaseHanError::InvalidCode(*code, *pos),
+            #[cfg(feature = "std")]
+            BaseHanError::IoError(e) => BaseHanError::IoError(io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+}
+
+impl PartialEq for BaseHanError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BaseHanError::InternalError(a), BaseHanError::InternalError(b)) => a == b,
+            (BaseHanError::InvalidCode(a, i), BaseHanError::InvalidCode(b, j)) => a == b && i == j,
+            #[cfg(feature = "std")]
+            (BaseHanError::IoError(a), BaseHanError::IoError(b)) => {
+                a.kind() == b.kind() && a.to_string() == b.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for BaseHanError {}
+
+impl fmt::Display for BaseHanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseHanError::InternalError(msg) => write!(f, "internal error: {}", msg),
+            BaseHanError::InvalidCode(code, pos) => write!(f, "invalid code {:#x} at pos {}", code, pos),
+            #[cfg(feature = "std")]
+            BaseHanError::IoError(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BaseHanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BaseHanError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BaseHanError {
+    fn from(e: io::Error) -> Self {
+        BaseHanError::IoError(e)
+    }
 }
 
 pub fn encode<T: AsRef<[u8]>>(raw: T) -> Result<String, BaseHanError> {
@@ -21,8 +108,8 @@ pub fn encode<T: AsRef<[u8]>>(raw: T) -> Result<String, BaseHanError> {
     let mut buff = 0u32;
     let mut bit_pointer = 0;
 
-    for i in 0..raw.len() {
-        buff = buff << 8 | raw[i] as u32;
+    for &byte in raw {
+        buff = buff << 8 | byte as u32;
         bit_pointer += 8;
         while bit_pointer >= 13 {
             bit_pointer -= 13;
@@ -67,12 +154,9 @@ pub fn encode<T: AsRef<[u8]>>(raw: T) -> Result<String, BaseHanError> {
     Ok(result.iter().collect())
 }
 
-pub fn decode(basehan: &String) -> Result<Vec<u8>, BaseHanError> {
-    // check multi bytes tail
-    let mut is_invalid = false;
-    let mut err_code = 0;
+pub fn decode(basehan: &str) -> Result<Vec<u8>, BaseHanError> {
     // check whether c is avaliable one by one
-    for (i, c) in basehan.chars().into_iter().enumerate() {
+    for (i, c) in basehan.chars().enumerate() {
         if ((c as u32) < BASE_OFFSET) || ((c as u32) > BASE_OFFSET + CODE_RANGE) {
             return Err(BaseHanError::InvalidCode(c as u32, i));
         }
@@ -88,8 +172,8 @@ pub fn decode(basehan: &String) -> Result<Vec<u8>, BaseHanError> {
     let mut buff = 0u32;
     let mut bit_pointer = 0;
 
-    for i in 0..basehan.len() - 1 {
-        let index = basehan[i] as u32 - BASE_OFFSET;
+    for &c in &basehan[..basehan.len() - 1] {
+        let index = c as u32 - BASE_OFFSET;
         buff = (buff << 13) | (index & 0x1FFF);
         bit_pointer += 13;
         while bit_pointer >= 8 {
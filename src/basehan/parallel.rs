@@ -0,0 +1,60 @@
+//! Encode large inputs on several threads.
+
+use rayon::prelude::*;
+
+use crate::basehan::v1::{BaseHanEncoder, BaseHanError};
+
+// 13 bytes are exactly 8 chars, so blocks of a multiple of 13 bytes encode independently.
+const ALIGNMENT: usize = 13;
+// The 8 chars of an aligned block are data chars, 3 bytes each in UTF-8
+const ALIGNED_UTF8_LEN: usize = 8 * 3;
+// Bytes encoded at a time within a block, to bound the chars in flight on each thread
+const CHUNK_SIZE: usize = ALIGNMENT * 1024;
+
+/// Encode `input` in one shot on `num_threads` threads (rayon's global pool if 0, or if a pool
+/// of `num_threads` threads cannot be built), terminator included.
+/// The output is the same as `BaseHanEncoder::encode_bytes_to_string`.
+pub fn parallel_encode(input: &[u8], num_threads: usize) -> Result<String, BaseHanError> {
+    if input.is_empty() {
+        return Ok(String::new());
+    }
+    let pool = match num_threads {
+        0 => None,
+        _ => rayon::ThreadPoolBuilder::new().num_threads(num_threads).build().ok(),
+    };
+    let threads = pool.as_ref().map_or_else(rayon::current_num_threads, |pool| pool.current_num_threads());
+    // Keep 1 to 13 bytes for the last block, which carries the terminator
+    let (body, tail) = input.split_at((input.len() - 1) / ALIGNMENT * ALIGNMENT);
+    let block_size = body.len().div_ceil(threads).div_ceil(ALIGNMENT).max(1) * ALIGNMENT;
+
+    // Each block is encoded straight into its own part of the output
+    let mut out = Vec::with_capacity(BaseHanEncoder::estimate_capacity(input.len()) * 3);
+    out.resize(body.len() / ALIGNMENT * ALIGNED_UTF8_LEN, 0);
+    let encode = |out: &mut [u8]| {
+        body.par_chunks(block_size)
+            .zip(out.par_chunks_mut(block_size / ALIGNMENT * ALIGNED_UTF8_LEN))
+            .try_for_each(|(block, out)| encode_block(block, out))
+    };
+    match &pool {
+        Some(pool) => pool.install(|| encode(&mut out))?,
+        None => encode(&mut out)?,
+    }
+
+    let mut out = String::from_utf8(out).expect("Encoded chars are valid UTF-8");
+    let mut encoder = BaseHanEncoder::with_buffer_size(0);
+    out.extend(encoder.update(tail)?);
+    out.push(encoder.finish()?);
+    Ok(out)
+}
+
+/// Encode the aligned `block` into `out`, exactly `ALIGNED_UTF8_LEN` bytes for every 13 bytes.
+fn encode_block(block: &[u8], out: &mut [u8]) -> Result<(), BaseHanError> {
+    let mut encoder = BaseHanEncoder::with_buffer_size(0);
+    let mut pos = 0;
+    for chunk in block.chunks(CHUNK_SIZE) {
+        for c in encoder.update(chunk)? {
+            pos += c.encode_utf8(&mut out[pos..]).len();
+        }
+    }
+    Ok(())
+}
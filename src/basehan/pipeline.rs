@@ -0,0 +1,113 @@
+//! Compress, then encode, in a single streaming transform.
+
+use std::io;
+use std::io::Write;
+
+use crate::basehan::v1::{BaseHanEncoder, BaseHanError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    #[cfg(feature = "flate2")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Builder of an `EncodingPipeline`.
+#[derive(Debug, Default, Clone)]
+pub struct BaseHanPipeline {
+    compression: Option<CompressionAlgorithm>,
+}
+
+impl BaseHanPipeline {
+    pub fn new() -> Self {
+        BaseHanPipeline { compression: None }
+    }
+
+    /// Compress the input with `algorithm` before encoding it.
+    pub fn compress(mut self, algorithm: CompressionAlgorithm) -> Self {
+        self.compression = Some(algorithm);
+        self
+    }
+
+    /// Build the pipeline, write the input to it and call `finish` for the encoded string.
+    pub fn encode(self) -> Result<EncodingPipeline, BaseHanError> {
+        let sink = Sink(BaseHanEncoder::new());
+        let stage = match self.compression {
+            None => Stage::Plain(sink),
+            #[cfg(feature = "flate2")]
+            Some(CompressionAlgorithm::Gzip) => {
+                Stage::Gzip(flate2::write::GzEncoder::new(sink, flate2::Compression::default()))
+            }
+            #[cfg(feature = "zstd")]
+            Some(CompressionAlgorithm::Zstd) => {
+                Stage::Zstd(zstd::Encoder::new(sink, 0).map_err(BaseHanError::IoError)?)
+            }
+        };
+        Ok(EncodingPipeline { stage })
+    }
+}
+
+/// Takes the encoder output only once the compressor is done, flushing has nothing to do with the terminator.
+struct Sink(BaseHanEncoder);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Stage {
+    Plain(Sink),
+    #[cfg(feature = "flate2")]
+    Gzip(flate2::write::GzEncoder<Sink>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, Sink>),
+}
+
+pub struct EncodingPipeline {
+    stage: Stage,
+}
+
+impl EncodingPipeline {
+    /// Finish the compression, then return the whole encoded string, terminator included.
+    pub fn finish(self) -> Result<String, BaseHanError> {
+        let sink = match self.stage {
+            Stage::Plain(sink) => sink,
+            #[cfg(feature = "flate2")]
+            Stage::Gzip(gzip) => gzip.finish().map_err(BaseHanError::IoError)?,
+            #[cfg(feature = "zstd")]
+            Stage::Zstd(zstd) => zstd.finish().map_err(BaseHanError::IoError)?,
+        };
+        let mut encoder = sink.0;
+        let mut out = String::from_iter(encoder.encoded_chars());
+        out.extend(encoder.finish_or_empty()?);
+        Ok(out)
+    }
+}
+
+impl Write for EncodingPipeline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.stage {
+            Stage::Plain(sink) => sink.write(buf),
+            #[cfg(feature = "flate2")]
+            Stage::Gzip(gzip) => gzip.write(buf),
+            #[cfg(feature = "zstd")]
+            Stage::Zstd(zstd) => zstd.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stage {
+            Stage::Plain(sink) => sink.flush(),
+            #[cfg(feature = "flate2")]
+            Stage::Gzip(gzip) => gzip.flush(),
+            #[cfg(feature = "zstd")]
+            Stage::Zstd(zstd) => zstd.flush(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Serialize bytes as Base-Han (v1) strings.
+//!
+//! Annotate a `Vec<u8>` field with `#[serde(with = "basehan::serde")]`,
+//! or wrap it in [`SerdeBaseHan`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+
+pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]> + ?Sized,
+    S: Serializer,
+{
+    let encoded = BaseHanEncoder::encode_bytes_to_string(bytes.as_ref())
+        .map_err(<S::Error as ser::Error>::custom)?;
+    serializer.serialize_str(&encoded)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = String::deserialize(deserializer)?;
+    BaseHanDecoder::decode_str_to_bytes(&encoded)
+        .map_err(<D::Error as de::Error>::custom)
+}
+
+/// Bytes that (de)serialize as a Base-Han string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerdeBaseHan(pub Vec<u8>);
+
+impl From<Vec<u8>> for SerdeBaseHan {
+    fn from(bytes: Vec<u8>) -> Self {
+        SerdeBaseHan(bytes)
+    }
+}
+
+impl From<SerdeBaseHan> for Vec<u8> {
+    fn from(wrapped: SerdeBaseHan) -> Self {
+        wrapped.0
+    }
+}
+
+impl Serialize for SerdeBaseHan {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerdeBaseHan {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(SerdeBaseHan)
+    }
+}
@@ -0,0 +1,88 @@
+//! Split whole 13-byte blocks into 13-bit groups in bulk, for the encoder's inner loop.
+//!
+//! 13 bytes are exactly 8 groups, so a block starting with an empty `BitCache13` leaves it empty.
+//! With AVX2 (detected at runtime) a block is split in one go, one group per 32-bit lane;
+//! otherwise, and on other targets, through a `u128`.
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+pub(crate) const BLOCK_SIZE: usize = 13;
+
+/// Call `f` with each 13-bit group of `blocks`, in order, most significant bits first.
+/// The bytes past the last whole block are ignored.
+pub(crate) fn for_each_group<F: FnMut(u32)>(blocks: &[u8], mut f: F) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 is available, as just checked
+        unsafe { for_each_group_avx2(blocks, &mut f) };
+        return;
+    }
+    for_each_group_scalar(blocks, &mut f);
+}
+
+fn for_each_group_scalar<F: FnMut(u32)>(blocks: &[u8], f: &mut F) {
+    for block in blocks.chunks_exact(BLOCK_SIZE) {
+        let mut bytes = [0u8; 16];
+        bytes[16 - BLOCK_SIZE..].copy_from_slice(block);
+        let bits = u128::from_be_bytes(bytes);
+        for i in (0..8).rev() {
+            f((bits >> (13 * i)) as u32 & 0x1fff);
+        }
+    }
+}
+
+// Group `i` starts at bit `13 * i` of the block, so it is within the 3 bytes from `13 * i / 8`.
+// Each lane gathers them big-endian into its low 24 bits (-1 zeroes the top byte), then drops
+// the `24 - 13 - 13 * i % 8` bits following the group.
+#[cfg(target_arch = "x86_64")]
+const SHUFFLE: [i8; 32] = {
+    let mut shuffle = [-1i8; 32];
+    let mut i = 0;
+    while i < 8 {
+        let start = (13 * i / 8) as i8;
+        shuffle[4 * i] = start + 2;
+        shuffle[4 * i + 1] = start + 1;
+        shuffle[4 * i + 2] = start;
+        i += 1;
+    }
+    shuffle
+};
+
+#[cfg(target_arch = "x86_64")]
+const SHIFTS: [i32; 8] = {
+    let mut shifts = [0i32; 8];
+    let mut i = 0;
+    while i < 8 {
+        shifts[i] = 11 - (13 * i % 8) as i32;
+        i += 1;
+    }
+    shifts
+};
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn for_each_group_avx2<F: FnMut(u32)>(blocks: &[u8], f: &mut F) {
+    // `_mm256_shuffle_epi8` shuffles within each 128-bit half, so both halves hold the block;
+    // group 7 reads byte 13, one past the block, but only keeps bits of bytes 11 and 12.
+    let shuffle = _mm256_loadu_si256(SHUFFLE.as_ptr() as *const __m256i);
+    let shifts = _mm256_loadu_si256(SHIFTS.as_ptr() as *const __m256i);
+    let mask = _mm256_set1_epi32(0x1fff);
+    let mut groups = [0u32; 8];
+    let mut last = [0u8; 16];
+    for (i, block) in blocks.chunks_exact(BLOCK_SIZE).enumerate() {
+        // Load 16 bytes straight from the input while there are enough, from a copy otherwise
+        let start = i * BLOCK_SIZE;
+        let bytes = match blocks.get(start..start + 16) {
+            Some(bytes) => bytes,
+            None => {
+                last[..BLOCK_SIZE].copy_from_slice(block);
+                &last
+            }
+        };
+        let block = _mm256_broadcastsi128_si256(_mm_loadu_si128(bytes.as_ptr() as *const __m128i));
+        let lanes = _mm256_srlv_epi32(_mm256_shuffle_epi8(block, shuffle), shifts);
+        _mm256_storeu_si256(groups.as_mut_ptr() as *mut __m256i, _mm256_and_si256(lanes, mask));
+        groups.iter().for_each(|&group| f(group));
+    }
+}
@@ -1,119 +1,1517 @@
+use alloc::string::String;
+use core::fmt;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+#[cfg(feature = "std")]
+use alloc::vec;
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+#[cfg(feature = "std")]
 use std::io;
-use std::num::Wrapping;
+#[cfg(feature = "std")]
+use std::io::{BufRead, Read, Write};
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver};
+#[cfg(feature = "std")]
+use std::thread::{self, JoinHandle};
 
 use crate::basehan::BASE_OFFSET;
-use crate::basehan::v1::BitCache8Out::{Double, Single};
+use crate::basehan::bits::BitCache8Out::{Double, Single};
+use crate::basehan::bits::{BitCache13, BitCache8};
+#[cfg(feature = "hmac")]
+use crate::basehan::integrity::{self, HmacSha256, Verifier};
+#[cfg(feature = "simd")]
+use crate::basehan::simd;
 
 const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024; // 1 MiB
-const ENDING_OFFSET: u32 = 0x6e00;
+/// Start of the terminator range `[ENDING_OFFSET, ENDING_OFFSET + 0x2000)`, right above the data
+/// chars `[0x4e00, ENDING_OFFSET)`. Chars returned by the encoder before `finish` are always
+/// below `ENDING_OFFSET`, only the terminator is at or above it.
+pub const ENDING_OFFSET: u32 = 0x6e00;
+pub(crate) const ENDING_RANGE: u32 = 0x2000; // 2^13 code points, up to 12 remaining bits behind a leading 1
+const CODE_RANGE: u32 = 0x2000; // 2^13 code points, one for each 13-bit group
+
+/// Appended after the terminator by `BaseHanEncoder::finish_padded`, skipped by the decoder.
+pub const PAD_CHAR: char = '\u{3000}'; // IDEOGRAPHIC SPACE
+
+/// Optionally prepended to the output to tell the version, see `BaseHanDecoder::auto_detect`.
+/// Version prefixes are `VERSION_OFFSET + version`, outside of the data and terminator ranges.
+pub const VERSION_PREFIX: char = '\u{9f01}';
+pub(crate) const VERSION_OFFSET: u32 = 0x9f00;
+
+/// Write the 3-byte UTF-8 of the BMP char `code` to `out` at `pos`.
+const fn put_utf8(out: &mut [u8], pos: usize, code: u32) {
+    out[pos] = 0xe0 | (code >> 12) as u8;
+    out[pos + 1] = 0x80 | (code >> 6 & 0x3f) as u8;
+    out[pos + 2] = 0x80 | (code & 0x3f) as u8;
+}
+
+/// The encoding of every single byte, i.e. its terminator with 8 remaining bits, back to back.
+const SINGLE_BYTE_TABLE: &str = {
+    const fn utf8() -> [u8; 256 * 3] {
+        let mut out = [0u8; 256 * 3];
+        let mut byte = 0;
+        while byte < 256 {
+            put_utf8(&mut out, byte * 3, ENDING_OFFSET + (1 << 8 | byte as u32));
+            byte += 1;
+        }
+        out
+    }
+    match core::str::from_utf8(&utf8()) {
+        Ok(table) => table,
+        Err(_) => panic!("Invalid UTF-8 in the single byte table"),
+    }
+};
+
+/// The encoding of every pair of bytes, i.e. a data char with the first 13 bits and
+/// a terminator with the 3 others, back to back, 6 bytes of UTF-8 each.
+static DOUBLE_BYTE_TABLE: &str = {
+    const fn utf8() -> [u8; 0x10000 * 6] {
+        let mut out = [0u8; 0x10000 * 6];
+        let mut pair = 0;
+        while pair < 0x10000 {
+            put_utf8(&mut out, pair * 6, BASE_OFFSET + (pair >> 3) as u32);
+            put_utf8(&mut out, pair * 6 + 3, ENDING_OFFSET + (1 << 3 | (pair & 7) as u32));
+            pair += 1;
+        }
+        out
+    }
+    match core::str::from_utf8(&utf8()) {
+        Ok(table) => table,
+        Err(_) => panic!("Invalid UTF-8 in the double byte table"),
+    }
+};
+
+/// The longest input `BaseHanEncoder::encode_bytes_to_cow` can borrow the output of:
+/// a table of n-byte inputs has 256^n entries, 384 KiB of text for 2 bytes already.
+pub const MAX_COW_THRESHOLD: usize = 2;
+
+const LEN_PREFIX_BYTES: usize = 4; // Big-endian u32 ahead of the metadata or the length-prefixed data
+
+/// Map the `nbits` remaining bits dumped from `BitCache13` to a char ranging from 0x6e00 to 0x8e00,
+/// indicating the end of stream.
+/// A leading 1 is prepended to mark how many bits there are, since 13 bits do not fit bytes evenly,
+/// the decoder cannot tell e.g. 0 from 8 remaining bits otherwise.
+fn terminator(remaining: u32, nbits: usize) -> char {
+    char::from_u32((1 << nbits | remaining) + ENDING_OFFSET)
+        .expect("Data cannot convert to a valid char, which should never happen.")
+}
+
+/// Split the value of a terminator back into the remaining bits and their number.
+fn terminator_bits(value: u32) -> Option<(u32, usize)> {
+    if value == 0 {
+        return None; // No leading 1
+    }
+    let nbits = 31 - value.leading_zeros();
+    Some((value ^ (1 << nbits), nbits as usize))
+}
+
+/// Push `c` to `out`, percent-encoding its UTF-8 in url-safe mode.
+fn push_encoded(out: &mut Vec<char>, c: char, url_safe: bool) {
+    if !url_safe {
+        out.push(c);
+        return;
+    }
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut utf8 = [0u8; 4];
+    for &byte in c.encode_utf8(&mut utf8).as_bytes() {
+        out.push('%');
+        out.push(HEX[(byte >> 4) as usize] as char);
+        out.push(HEX[(byte & 0xf) as usize] as char);
+    }
+}
+
+/// Reassemble chars from their percent-encoded UTF-8, one input char at a time.
+#[derive(Default, Clone)]
+struct PercentDecoder {
+    utf8: [u8; 4],
+    len: usize,
+    high: Option<u8>, // First hex digit of the current byte
+    escaping: bool, // '%' seen, byte not complete yet
+}
+
+impl PercentDecoder {
+    /// Whether `c` must go through `push`.
+    fn accepts(&self, c: char) -> bool {
+        c == '%' || self.escaping || self.len > 0
+    }
+
+    /// Return the char once its last byte is complete, `Err` on malformed input.
+    fn push(&mut self, c: char) -> Result<Option<char>, ()> {
+        if !self.escaping {
+            if c != '%' {
+                return Err(()); // Incomplete char
+            }
+            self.escaping = true;
+            return Ok(None);
+        }
+        let digit = c.to_digit(16).ok_or(())? as u8;
+        let Some(high) = self.high.take() else {
+            self.high = Some(digit);
+            return Ok(None);
+        };
+        self.escaping = false;
+        self.utf8[self.len] = high << 4 | digit;
+        self.len += 1;
+        let expected = match self.utf8[0] {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Err(()),
+        };
+        if self.len < expected {
+            return Ok(None);
+        }
+        let out = core::str::from_utf8(&self.utf8[..self.len]).map_err(|_| ())?.chars().next();
+        self.len = 0;
+        Ok(out)
+    }
+}
+
+/// Chars the decoder ignores: line wrapping and padding.
+pub(crate) fn is_skipped(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n' | PAD_CHAR)
+}
 
 #[derive(Debug)]
 pub enum BaseHanError {
+    #[cfg(feature = "std")]
     IoError(io::Error),
     EndOfFile, // Remaining byte in BitCache
+    InvalidOffset(u32),
+    InvalidAlphabet,
+    EmptyInput, // Finishing an encoder fed with no byte
+    TruncatedStream, // Finishing a decoder before the terminator
+    InvalidRange, // Offset & length out of the input slice
+    IntegrityFailure, // HMAC tag not matching the decoded bytes
+    ExceedsTarget(usize), // Encoded length over the length to pad to
+    OddLength(usize), // Decoded byte count not fitting u16s
+    InvalidCode(u32, usize), // Code point & its position in the chunk
+    InvalidTerminator(u32, usize), // Terminator not ending on a byte boundary & its position in the chunk
+    BufferTooSmall(usize), // Size needed for the output
+    CodeOutOfRange { code: u32, pos: usize, valid_min: u32, valid_max: u32 }, // Valid chars are all within valid_min..=valid_max
+    InvalidHex, // Odd length or non-hex digit in the input of `encode_hex_string`
+}
+
+// `io::Error` is neither `Clone` nor `PartialEq`, IO errors are compared and cloned by kind & message.
+impl Clone for BaseHanError {
+    fn clone(&self) -> Self {
+        match self {
+            #[cfg(feature = "std")]
+            BaseHanError::IoError(e) => BaseHanError::IoError(io::Error::new(e.kind(), e.to_string())),
+            BaseHanError::EndOfFile => BaseHanError::EndOfFile,
+            BaseHanError::InvalidOffset(offset) => BaseHanError::InvalidOffset(*offset),
+            BaseHanError::InvalidAlphabet => BaseHanError::InvalidAlphabet,
+            BaseHanError::EmptyInput => BaseHanError::EmptyInput,
+            BaseHanError::TruncatedStream => BaseHanError::TruncatedStream,
+            BaseHanError::InvalidRange => BaseHanError::InvalidRange,
+            BaseHanError::IntegrityFailure => BaseHanError::IntegrityFailure,
+            BaseHanError::ExceedsTarget(len) => BaseHanError::ExceedsTarget(*len),
+            BaseHanError::InvalidCode(code, pos) => BaseHanError::InvalidCode(*code, *pos),
+            BaseHanError::OddLength(len) => BaseHanError::OddLength(*len),
+            BaseHanError::InvalidTerminator(code, pos) => BaseHanError::InvalidTerminator(*code, *pos),
+            BaseHanError::BufferTooSmall(needed) => BaseHanError::BufferTooSmall(*needed),
+            BaseHanError::CodeOutOfRange { code, pos, valid_min, valid_max } => BaseHanError::CodeOutOfRange {
+                code: *code,
+                pos: *pos,
+                valid_min: *valid_min,
+                valid_max: *valid_max,
+            },
+            BaseHanError::InvalidHex => BaseHanError::InvalidHex,
+        }
+    }
+}
+
+impl PartialEq for BaseHanError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            #[cfg(feature = "std")]
+            (BaseHanError::IoError(a), BaseHanError::IoError(b)) => {
+                a.kind() == b.kind() && a.to_string() == b.to_string()
+            }
+            (BaseHanError::EndOfFile, BaseHanError::EndOfFile) => true,
+            (BaseHanError::InvalidOffset(a), BaseHanError::InvalidOffset(b)) => a == b,
+            (BaseHanError::InvalidAlphabet, BaseHanError::InvalidAlphabet) => true,
+            (BaseHanError::EmptyInput, BaseHanError::EmptyInput) => true,
+            (BaseHanError::TruncatedStream, BaseHanError::TruncatedStream) => true,
+            (BaseHanError::InvalidRange, BaseHanError::InvalidRange) => true,
+            (BaseHanError::IntegrityFailure, BaseHanError::IntegrityFailure) => true,
+            (BaseHanError::ExceedsTarget(a), BaseHanError::ExceedsTarget(b)) => a == b,
+            (BaseHanError::InvalidCode(a, i), BaseHanError::InvalidCode(b, j)) => a == b && i == j,
+            (BaseHanError::OddLength(a), BaseHanError::OddLength(b)) => a == b,
+            (BaseHanError::InvalidTerminator(a, i), BaseHanError::InvalidTerminator(b, j)) => a == b && i == j,
+            (BaseHanError::BufferTooSmall(a), BaseHanError::BufferTooSmall(b)) => a == b,
+            (
+                BaseHanError::CodeOutOfRange { code: a, pos: i, valid_min: a_min, valid_max: a_max },
+                BaseHanError::CodeOutOfRange { code: b, pos: j, valid_min: b_min, valid_max: b_max },
+            ) => a == b && i == j && a_min == b_min && a_max == b_max,
+            (BaseHanError::InvalidHex, BaseHanError::InvalidHex) => true,
+            _ => false,
+        }
+    }
 }
 
+impl Eq for BaseHanError {}
+
+impl fmt::Display for BaseHanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            BaseHanError::IoError(e) => write!(f, "IO error: {}", e),
+            BaseHanError::EndOfFile => write!(f, "input continues after the end of stream"),
+            BaseHanError::InvalidOffset(offset) => write!(f, "invalid code point offset {:#x}", offset),
+            BaseHanError::InvalidAlphabet => write!(
+                f,
+                "invalid alphabet: chars must be ascending, without terminators or whitespace"
+            ),
+            BaseHanError::EmptyInput => write!(f, "no input to encode"),
+            BaseHanError::TruncatedStream => write!(f, "input ends before the terminator"),
+            BaseHanError::InvalidRange => write!(f, "range out of the input bounds"),
+            BaseHanError::IntegrityFailure => write!(f, "HMAC tag mismatch"),
+            BaseHanError::ExceedsTarget(len) => write!(f, "{} encoded chars exceed the padded length", len),
+            BaseHanError::InvalidCode(code, pos) => write!(f, "invalid code {:#x} at pos {}", code, pos),
+            BaseHanError::OddLength(len) => write!(f, "{} bytes cannot be read as 16-bit values", len),
+            BaseHanError::InvalidTerminator(code, pos) => {
+                write!(f, "terminator {:#x} at pos {} does not match the preceding chars", code, pos)
+            }
+            BaseHanError::BufferTooSmall(needed) => write!(f, "output buffer too small, {} bytes needed", needed),
+            BaseHanError::CodeOutOfRange { code, pos, valid_min, valid_max } => write!(
+                f,
+                "code {:#x} at pos {} out of the valid range {:#x}..={:#x}",
+                code, pos, valid_min, valid_max
+            ),
+            BaseHanError::InvalidHex => write!(f, "invalid hex string"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BaseHanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BaseHanError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Check that `base_offset..base_offset + CODE_RANGE` are all valid chars
+/// and do not collide with the terminator range.
+fn validate_offset(base_offset: u32) -> Result<(), BaseHanError> {
+    let end = match base_offset.checked_add(CODE_RANGE) {
+        Some(end) if end <= 0x110000 => end,
+        _ => return Err(BaseHanError::InvalidOffset(base_offset)),
+    };
+    let overlaps = |start: u32, stop: u32| base_offset < stop && start < end;
+    if overlaps(0xd800, 0xe000) || overlaps(ENDING_OFFSET, ENDING_OFFSET + ENDING_RANGE) {
+        return Err(BaseHanError::InvalidOffset(base_offset));
+    }
+    Ok(())
+}
+
+/// Whether `base_offset` is accepted by `with_offset`: the 8192 chars from it are valid Unicode
+/// scalar values (below 0x110000, no surrogate) and do not overlap the terminators from `ENDING_OFFSET`.
+pub fn verify_encoding_range(base_offset: u32) -> bool {
+    validate_offset(base_offset).is_ok()
+}
+
+/// Check that the alphabet is strictly ascending (so it can be binary searched)
+/// and contains neither terminators nor the whitespace skipped by the decoder.
+fn validate_alphabet(alphabet: &[char; CODE_RANGE as usize]) -> Result<(), BaseHanError> {
+    let sorted = alphabet.windows(2).all(|w| w[0] < w[1]);
+    let clashes = alphabet.iter().any(|&c| {
+        (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&(c as u32))
+            || is_skipped(c)
+    });
+    if !sorted || clashes {
+        return Err(BaseHanError::InvalidAlphabet);
+    }
+    Ok(())
+}
+
+/// The chars 13-bit groups are mapped to.
+#[derive(Clone, Copy)]
+enum Alphabet {
+    Offset(u32),
+    Custom(&'static [char; CODE_RANGE as usize]),
+}
+
+impl Alphabet {
+    fn char_at(&self, index: u32) -> char {
+        match self {
+            Alphabet::Offset(base_offset) => char::from_u32(index + base_offset)
+                .expect("Data cannot convert to a valid char, which should never happen."),
+            Alphabet::Custom(alphabet) => alphabet[index as usize],
+        }
+    }
+
+    fn index_of(&self, c: char) -> Option<u32> {
+        match self {
+            Alphabet::Offset(base_offset) => {
+                let code = c as u32;
+                (*base_offset..base_offset + CODE_RANGE)
+                    .contains(&code)
+                    .then(|| code - base_offset)
+            }
+            Alphabet::Custom(alphabet) => alphabet.binary_search(&c).ok().map(|i| i as u32),
+        }
+    }
+
+    /// The smallest range holding all the data chars and the terminators.
+    fn valid_range(&self) -> (u32, u32) {
+        let (first, last) = match self {
+            Alphabet::Offset(base_offset) => (*base_offset, base_offset + CODE_RANGE - 1),
+            Alphabet::Custom(alphabet) => (alphabet[0] as u32, alphabet[alphabet.len() - 1] as u32),
+        };
+        (first.min(ENDING_OFFSET), last.max(ENDING_OFFSET + ENDING_RANGE - 1))
+    }
+
+    /// `CodeOutOfRange` for `code` at `pos`.
+    fn out_of_range(&self, code: u32, pos: usize) -> BaseHanError {
+        let (valid_min, valid_max) = self.valid_range();
+        BaseHanError::CodeOutOfRange { code, pos, valid_min, valid_max }
+    }
+}
+
+/// Streaming v1 encoder.
+///
+/// The last bits of the input only come out with the terminator, so a stream must end with
+/// `finish` (or `finish_chars` / `finish_padded`, or a `flush` through `Write`). An encoder dropped
+/// without finishing leaves an output the decoder reports as `TruncatedStream`.
+///
+/// Cloning checkpoints the stream, e.g. to encode a common prefix once and branch into
+/// different suffixes.
+///
+/// The encoder owns all of its state, so it is `Send` and `Sync` (and so is `BaseHanDecoder`):
+/// it can move to another thread, or be shared behind a lock, e.g. a
+/// `static ENCODER: LazyLock<Mutex<BaseHanEncoder>>` that threads of a server encode through
+/// with `ENCODER.lock().unwrap().update(chunk)`. Streams from different threads sharing one
+/// encoder interleave, so keep one encoder per stream unless the lock is held until `reset`.
+#[derive(Clone)]
+pub struct BaseHanEncoder {
+    buf_out: Vec<char>,
+    remainings: BitCache13,
+    alphabet: Alphabet,
+    line_width: usize, // 0 for no wrapping
+    line_separator: &'static str,
+    column: usize,
+    nchars: usize, // Encoded chars so far, line separators excluded
+    nbytes: usize, // Bytes fed so far
+    url_safe: bool,
+    header: bool,
+    chunk_size: usize, // For encode_file
+    #[cfg(feature = "hmac")]
+    hmac: Option<HmacSha256>,
+}
+
+impl Default for BaseHanEncoder {
+    fn default() -> Self {
+        BaseHanEncoder::new()
+    }
+}
+
+impl BaseHanEncoder {
+    pub fn new() -> Self {
+        BaseHanEncoderBuilder::new().assemble()
+    }
+
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        BaseHanEncoderBuilder::new().buffer_size(buffer_size).assemble()
+    }
+
+    /// Encode 13-bit groups to `[base_offset, base_offset + 0x2000)` instead of the default range.
+    /// The decoder must be constructed with the same offset.
+    pub fn with_offset(base_offset: u32) -> Result<Self, BaseHanError> {
+        BaseHanEncoderBuilder::new().base_offset(base_offset).build()
+    }
+
+    /// Encode 13-bit groups to the chars of a custom alphabet, which must be sorted in ascending order.
+    /// The decoder must be constructed with the same alphabet.
+    pub fn with_alphabet(alphabet: &'static [char; 8192]) -> Result<Self, BaseHanError> {
+        BaseHanEncoderBuilder::new().alphabet(alphabet).build()
+    }
+
+    /// Insert a line separator (`\n` by default) after every `line_width` encoded chars.
+    pub fn with_line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Start a `BaseHanEncoderBuilder` to combine settings.
+    pub fn builder() -> BaseHanEncoderBuilder {
+        BaseHanEncoderBuilder::new()
+    }
+
+    /// Use `line_separator` when wrapping lines.
+    /// The decoder skips whitespace only, so the separator should consist of whitespace.
+    pub fn line_separator(mut self, line_separator: &'static str) -> Self {
+        self.line_separator = line_separator;
+        self
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(level = "trace")))]
+    pub fn update<T>(&mut self, chunk: T) -> Result<Vec<char>, BaseHanError>
+    where
+        T: AsRef<[u8]>,
+    {
+        self.fill_buf_out_slice(chunk.as_ref());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(bytes = chunk.as_ref().len(), chars = self.buf_out.len());
+
+        let buf_out = core::mem::take(&mut self.buf_out); // Replace buffer with new & return the taken value
+        Ok(buf_out)
+    }
+
+    /// Like `update`, but append the chars to `out` rather than returning a new `Vec`.
+    /// Return the number of chars appended.
+    pub fn update_into(&mut self, chunk: &[u8], out: &mut String) -> Result<usize, BaseHanError> {
+        self.fill_buf_out_slice(chunk);
+        let n = self.buf_out.len();
+        out.extend(self.buf_out.drain(..)); // The buffer stays allocated for the next chunk
+        Ok(n)
+    }
+
+    /// Encode the `len` bytes of `buf` starting at `offset`.
+    /// Return `InvalidRange` if they are not all within `buf`.
+    pub fn update_slice(&mut self, buf: &[u8], offset: usize, len: usize) -> Result<Vec<char>, BaseHanError> {
+        let range = offset
+            .checked_add(len)
+            .and_then(|end| buf.get(offset..end))
+            .ok_or(BaseHanError::InvalidRange)?;
+        self.update(range)
+    }
+
+    /// Encode the first `n` bytes of `data` and return two independent copies of the encoder
+    /// at that point, e.g. to encode messages sharing a prefix. The chars of the prefix stay
+    /// pending in both, coming with the next `update` or `into_chars`.
+    /// Return `InvalidRange` if `data` is shorter than `n`.
+    pub fn split_at_bytes(mut self, n: usize, data: &[u8]) -> Result<(BaseHanEncoder, BaseHanEncoder), BaseHanError> {
+        let prefix = data.get(..n).ok_or(BaseHanError::InvalidRange)?;
+        self.fill_buf_out(prefix.iter().copied());
+        Ok((self.clone(), self))
+    }
+
+    /// Output ASCII only, by percent-encoding the UTF-8 of each char (`%E4%B8%80`),
+    /// for protocols that cannot carry Unicode. The decoder accepts this form transparently.
+    /// Note that `finish` returns the raw terminator, use `finish_chars` for the percent-encoded one.
+    pub fn url_safe(mut self) -> Self {
+        self.url_safe = true;
+        self
+    }
+
+    /// Read `chunk_size` bytes at a time in `encode_file`, `DEFAULT_BUFFER_SIZE` by default.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Start every non-empty stream with `VERSION_PREFIX`, so that Base-Han text can be told
+    /// apart from Chinese text. Decode it with `BaseHanDecoder::with_header` or `auto_detect`.
+    /// With a custom offset or alphabet containing `VERSION_PREFIX`, the header is ambiguous.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Append 8 chars of HMAC-SHA256 tag (truncated to 13 bytes) of the input after the terminator,
+    /// for `BaseHanDecoder::with_hmac_verify`. The tag comes with `finish_chars` and the other
+    /// finishing methods returning chars, not with `finish`.
+    #[cfg(feature = "hmac")]
+    pub fn with_hmac(mut self, key: &[u8]) -> Self {
+        self.hmac = Some(integrity::new_mac(key));
+        self
+    }
+
+    /// Encode everything from `input` into `output`, terminator included.
+    /// Empty input writes nothing, since there is nothing to terminate.
+    /// Return the number of bytes read.
+    #[cfg(feature = "std")]
+    pub fn encode_file<R, W>(mut self, mut input: R, mut output: W) -> Result<u64, BaseHanError>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut buf = vec![0u8; self.chunk_size.max(1)]; // An empty buffer would read as EOF
+        let mut total = 0u64;
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(BaseHanError::IoError(e)),
+            };
+            total += n as u64;
+            let out = String::from_iter(self.update(&buf[..n])?);
+            output.write_all(out.as_bytes()).map_err(BaseHanError::IoError)?;
+        }
+        if self.nbytes > 0 {
+            let terminator = String::from_iter(self.finish_chars()?);
+            output.write_all(terminator.as_bytes()).map_err(BaseHanError::IoError)?;
+        }
+        output.flush().map_err(BaseHanError::IoError)?;
+        Ok(total)
+    }
+
+    /// Encode everything from `reader` with `encode_all`, reading `DEFAULT_BUFFER_SIZE` bytes
+    /// at a time unless set otherwise with `with_chunk_size`.
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: Read>(reader: R) -> ReaderBaseHanEncoder<R> {
+        ReaderBaseHanEncoder {
+            reader,
+            encoder: BaseHanEncoder::new(),
+        }
+    }
+
+    /// Encode stdin in a background thread with `spawn_from_reader`.
+    #[cfg(feature = "std")]
+    pub fn spawn_from_stdin() -> (JoinHandle<Result<(), BaseHanError>>, Receiver<Vec<char>>) {
+        BaseHanEncoder::spawn_from_reader(io::stdin())
+    }
+
+    /// Read `reader` until EOF in a background thread, sending the chars encoded from every
+    /// `DEFAULT_BUFFER_SIZE` bytes, then the terminator, over the returned channel.
+    /// The thread returns the read error, if any, and stops early if the receiver is dropped.
+    #[cfg(feature = "std")]
+    pub fn spawn_from_reader<R>(mut reader: R) -> (JoinHandle<Result<(), BaseHanError>>, Receiver<Vec<char>>)
+    where
+        R: Read + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut encoder = BaseHanEncoder::new();
+            let mut buf = vec![0u8; DEFAULT_BUFFER_SIZE];
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(BaseHanError::IoError(e)),
+                };
+                let chars = encoder.update(&buf[..n])?;
+                if !chars.is_empty() && sender.send(chars).is_err() {
+                    return Ok(()); // Nobody is listening anymore
+                }
+            }
+            if let Some(terminator) = encoder.finish_or_empty()? {
+                let _ = sender.send(vec![terminator]);
+            }
+            Ok(())
+        });
+        (handle, receiver)
+    }
+
+    /// Encode bytes pulled from an iterator, without collecting them first.
+    pub fn update_iter<I>(&mut self, iter: I) -> Result<Vec<char>, BaseHanError>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        self.fill_buf_out(iter);
+
+        let buf_out = core::mem::take(&mut self.buf_out);
+        Ok(buf_out)
+    }
+
+    /// Encode `count` times `byte`, e.g. for padding. 13 bytes make exactly 8 chars and leave
+    /// the bit cache as it was, so every 13-byte block but the first encodes the same way and
+    /// is copied rather than encoded again, unless lines are wrapped.
+    pub fn encode_repeat(&mut self, byte: u8, count: usize) -> Result<Vec<char>, BaseHanError> {
+        const BLOCK_LEN: usize = 13;
+        let block = [byte; BLOCK_LEN];
+        let nblocks = count / BLOCK_LEN;
+        if self.line_width != 0 || nblocks < 2 {
+            self.fill_buf_out(core::iter::repeat_n(byte, count));
+            return Ok(core::mem::take(&mut self.buf_out));
+        }
+        // The first block flushes the bits of the previous input out of the cache
+        self.fill_buf_out(block);
+        let start = self.buf_out.len();
+        self.fill_buf_out(block);
+        let end = self.buf_out.len();
+        for _ in 2..nblocks {
+            self.buf_out.extend_from_within(start..end);
+            #[cfg(feature = "hmac")]
+            if let Some(mac) = &mut self.hmac {
+                hmac::Mac::update(mac, &block);
+            }
+        }
+        self.nbytes += (nblocks - 2) * BLOCK_LEN;
+        self.nchars += (nblocks - 2) * 8;
+        self.fill_buf_out(core::iter::repeat_n(byte, count % BLOCK_LEN));
+        Ok(core::mem::take(&mut self.buf_out))
+    }
+
+    /// Encode 16-bit values as little-endian bytes.
+    pub fn update_u16_le(&mut self, chunk: &[u16]) -> Result<Vec<char>, BaseHanError> {
+        self.update_iter(chunk.iter().flat_map(|v| v.to_le_bytes()))
+    }
+
+    /// Encode 16-bit values as big-endian bytes.
+    pub fn update_u16_be(&mut self, chunk: &[u16]) -> Result<Vec<char>, BaseHanError> {
+        self.update_iter(chunk.iter().flat_map(|v| v.to_be_bytes()))
+    }
+
+    /// Encode the UTF-8 bytes of `s`.
+    pub fn update_str(&mut self, s: &str) -> Result<Vec<char>, BaseHanError> {
+        self.update(s.as_bytes())
+    }
+
+    /// Number of chars `input_len` bytes encode to, terminator included, for `Vec::with_capacity`.
+    /// Line separators and percent-encoding are not accounted for.
+    pub const fn estimate_capacity(input_len: usize) -> usize {
+        crate::encoded_char_count(input_len)
+    }
+
+    /// Encode the whole input in one shot, terminator included.
+    /// Empty input encodes to an empty string.
+    pub fn encode_bytes_to_string(input: &[u8]) -> Result<String, BaseHanError> {
+        let mut encoder = BaseHanEncoder::with_buffer_size(BaseHanEncoder::estimate_capacity(input.len()));
+        let mut out = String::from_iter(encoder.update(input)?);
+        out.extend(encoder.finish_or_empty()?);
+        Ok(out)
+    }
+
+    /// Encode the bytes of a hex string, e.g. a hash, in one shot, terminator included.
+    /// Both cases are accepted. Return `InvalidHex` if `hex` has an odd length or a non-hex digit.
+    #[cfg(feature = "hex")]
+    pub fn encode_hex_string(hex: &str) -> Result<String, BaseHanError> {
+        let bytes = hex::decode(hex).map_err(|_| BaseHanError::InvalidHex)?;
+        BaseHanEncoder::encode_bytes_to_string(&bytes)
+    }
+
+    /// Like `encode_bytes_to_string`, but borrow the output from a static table rather than
+    /// allocating it for inputs of at most `N` bytes, e.g. `encode_bytes_to_cow::<1>(token)`.
+    /// `N` is at most `MAX_COW_THRESHOLD`, checked at compile time.
+    pub fn encode_bytes_to_cow<const N: usize>(input: &[u8]) -> Result<Cow<'static, str>, BaseHanError> {
+        const { assert!(N <= MAX_COW_THRESHOLD, "N must be at most MAX_COW_THRESHOLD") };
+        if input.len() > N {
+            return BaseHanEncoder::encode_bytes_to_string(input).map(Cow::Owned);
+        }
+        match *input {
+            [] => Ok(Cow::Borrowed("")),
+            [byte] => {
+                let start = byte as usize * 3; // Each terminator is 3 bytes in UTF-8
+                Ok(Cow::Borrowed(&SINGLE_BYTE_TABLE[start..start + 3]))
+            }
+            [first, second] => {
+                let start = u16::from_be_bytes([first, second]) as usize * 6;
+                Ok(Cow::Borrowed(&DOUBLE_BYTE_TABLE[start..start + 6]))
+            }
+            _ => unreachable!("N is at most MAX_COW_THRESHOLD"),
+        }
+    }
+
+    /// Encode `input` at compile time with the default alphabet, terminator included.
+    /// `M` must be `encoded_char_count(N)`, which stable Rust cannot compute from `N` in the
+    /// signature, so let the caller spell it out, e.g.
+    /// `static ENCODED: [char; encoded_char_count(RAW.len())] = BaseHanEncoder::encode_const(RAW);`.
+    /// Panics, i.e. fails to compile in a const context, if `M` is wrong.
+    pub const fn encode_const<const N: usize, const M: usize>(input: &[u8; N]) -> [char; M] {
+        assert!(M == crate::encoded_char_count(N), "M must be encoded_char_count(N)");
+        const fn char_at(code: u32) -> char {
+            match char::from_u32(code) {
+                Some(c) => c,
+                None => panic!("Data cannot convert to a valid char, which should never happen."),
+            }
+        }
+        let mut out = ['\0'; M];
+        // The same bit-packing as `BitCache13`, which is not const
+        let mut cache = 0u32;
+        let mut nbits = 0;
+        let mut i = 0;
+        let mut j = 0;
+        while i < N {
+            cache = cache << 8 | input[i] as u32;
+            nbits += 8;
+            if nbits >= 13 {
+                nbits -= 13;
+                out[j] = char_at(BASE_OFFSET + (cache >> nbits));
+                cache &= (1 << nbits) - 1;
+                j += 1;
+            }
+            i += 1;
+        }
+        if N > 0 {
+            out[j] = char_at(ENDING_OFFSET + (1 << nbits | cache));
+        }
+        out
+    }
+
+    /// Encode each payload as an independent message, terminator included, joined by `delimiter`.
+    /// Return `InvalidCode` if `delimiter` could be part of a message.
+    pub fn encode_chunked(payloads: &[&[u8]], delimiter: char) -> Result<String, BaseHanError> {
+        let code = delimiter as u32;
+        if (BASE_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code) || is_skipped(delimiter) {
+            return Err(BaseHanError::InvalidCode(code, 0));
+        }
+        let mut out = String::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            if i > 0 {
+                out.push(delimiter);
+            }
+            out += &BaseHanEncoder::encode_bytes_to_string(payload)?;
+        }
+        Ok(out)
+    }
+
+    /// Like `encode_bytes_to_string`, behind `VERSION_PREFIX`.
+    pub fn encode_bytes_to_prefixed_string(input: &[u8]) -> Result<String, BaseHanError> {
+        let mut out = String::from(VERSION_PREFIX);
+        out += &BaseHanEncoder::encode_bytes_to_string(input)?;
+        Ok(out)
+    }
+
+    /// Encode `meta`, e.g. a MIME type, and `data` as a single message: the length of `meta` as
+    /// a 4-byte big-endian integer, `meta`, then `data`. Split them with `decode_with_metadata`.
+    /// Return `InvalidRange` if `meta` is 4 GiB or more.
+    pub fn encode_with_metadata(data: &[u8], meta: &[u8]) -> Result<String, BaseHanError> {
+        let meta_len = u32::try_from(meta.len()).map_err(|_| BaseHanError::InvalidRange)?;
+        let total = LEN_PREFIX_BYTES + meta.len() + data.len();
+        let mut encoder = BaseHanEncoder::with_buffer_size(BaseHanEncoder::estimate_capacity(total));
+        let mut out = String::from_iter(encoder.update(meta_len.to_be_bytes())?);
+        out.extend(encoder.update(meta)?);
+        out.extend(encoder.update(data)?);
+        out.push(encoder.finish()?);
+        Ok(out)
+    }
+
+    /// Encode `bits` 13 at a time, most significant first, rather than padding them to whole bytes.
+    /// The terminator carries the last `bits.len() % 13`, so the exact number of bits survives,
+    /// see `BaseHanDecoder::decode_boolean_array`. Empty input encodes to an empty string.
+    /// Bits whose number is a multiple of 8 encode like the bytes they pack into.
+    pub fn encode_boolean_array(bits: &[bool]) -> Result<String, BaseHanError> {
+        if bits.is_empty() {
+            return Ok(String::new());
+        }
+        let code_bits = CODE_RANGE.trailing_zeros() as usize;
+        let mut out = String::with_capacity((bits.len() / code_bits + 1) * 3); // 3 UTF-8 bytes per char
+        let mut groups = bits.chunks_exact(code_bits);
+        let pack = |group: &[bool]| group.iter().fold(0u32, |acc, &bit| acc << 1 | bit as u32);
+        for group in groups.by_ref() {
+            out.push(Alphabet::Offset(BASE_OFFSET).char_at(pack(group)));
+        }
+        let rest = groups.remainder();
+        out.push(terminator(pack(rest), rest.len()));
+        Ok(out)
+    }
+
+    /// Encode the length of `data` as a 4-byte big-endian integer followed by `data`, as a single
+    /// message. Read it back with `BaseHanDecoder::decode_length_prefixed`.
+    /// Return `InvalidRange` if `data` is 4 GiB or more.
+    pub fn encode_length_prefixed(data: &[u8]) -> Result<String, BaseHanError> {
+        let len = u32::try_from(data.len()).map_err(|_| BaseHanError::InvalidRange)?;
+        let total = LEN_PREFIX_BYTES + data.len();
+        let mut encoder = BaseHanEncoder::with_buffer_size(BaseHanEncoder::estimate_capacity(total));
+        let mut out = String::from_iter(encoder.update(len.to_be_bytes())?);
+        out.extend(encoder.update(data)?);
+        out.push(encoder.finish()?);
+        Ok(out)
+    }
+
+    /// Drop any pending output and remaining bits, keeping the settings and the allocated buffer,
+    /// so that the encoder can be reused for another stream.
+    pub fn reset(&mut self) -> &mut Self {
+        self.buf_out.clear();
+        self.remainings = BitCache13::default();
+        self.column = 0;
+        self.nchars = 0;
+        self.nbytes = 0;
+        #[cfg(feature = "hmac")]
+        if let Some(mac) = &mut self.hmac {
+            hmac::Mac::reset(mac);
+        }
+        self
+    }
+
+    /// Drain the chars accumulated by `write` calls.
+    pub fn encoded_chars(&mut self) -> Vec<char> {
+        core::mem::take(&mut self.buf_out)
+    }
+
+    /// Number of chars accumulated by `write` calls and not drained yet.
+    pub fn pending_chars(&self) -> usize {
+        self.buf_out.len()
+    }
+
+    /// Drain the chars accumulated by `write` calls into `writer` as UTF-8.
+    /// On failure the chars are kept, though `writer` may have taken part of them.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&mut self, mut writer: W) -> Result<(), BaseHanError> {
+        let out = String::from_iter(&self.buf_out);
+        writer.write_all(out.as_bytes()).map_err(BaseHanError::IoError)?;
+        self.buf_out.clear();
+        Ok(())
+    }
+
+    /// Dump the remaining bits out as the terminator.
+    /// Return `EmptyInput` if no byte has been fed, in which case there is nothing to terminate.
+    #[must_use = "dropping the encoder without calling finish produces an incomplete encoding"]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(level = "trace")))]
+    pub fn finish(self) -> Result<char, BaseHanError> {
+        if self.nbytes == 0 {
+            return Err(BaseHanError::EmptyInput);
+        }
+        let nbits = self.remainings.nbits();
+        #[cfg(feature = "tracing")]
+        tracing::trace!(total_bytes = self.nbytes, total_chars = self.nchars + 1, nbits);
+        Ok(terminator(self.remainings.dump(), nbits))
+    }
+
+    /// Dump the remaining bits out, followed by `PAD_CHAR`s so that the number of encoded chars
+    /// (header and terminator included, line separators excluded) is a multiple of `multiple`.
+    pub fn finish_padded(self, multiple: usize) -> Result<Vec<char>, BaseHanError> {
+        let total = self.finished_len();
+        let npad = match multiple {
+            0 => 0,
+            _ => (multiple - total % multiple) % multiple,
+        };
+        let mut out = self.finish_chars()?;
+        out.resize(out.len() + npad, PAD_CHAR);
+        Ok(out)
+    }
+
+    /// Dump the remaining bits out, followed by `PAD_CHAR`s so that there are exactly `target_len`
+    /// encoded chars (header and terminator included, line separators excluded), e.g. for fixed-size messages.
+    /// The decoder skips the padding. Return `ExceedsTarget` if the encoding is already longer.
+    pub fn pad_to(self, target_len: usize) -> Result<Vec<char>, BaseHanError> {
+        let total = self.finished_len();
+        if total > target_len {
+            return Err(BaseHanError::ExceedsTarget(total));
+        }
+        let mut out = self.finish_chars()?;
+        out.resize(out.len() + target_len - total, PAD_CHAR);
+        Ok(out)
+    }
+
+    /// Number of encoded chars once finished, header included and line separators excluded.
+    fn finished_len(&self) -> usize {
+        let len = usize::from(self.header) + self.nchars + 1;
+        #[cfg(feature = "hmac")]
+        if self.hmac.is_some() {
+            return len + integrity::TAG_CHARS;
+        }
+        len
+    }
+
+    /// Like `finish`, but the terminator is in the output form, i.e. percent-encoded in url-safe mode,
+    /// and followed by the HMAC tag if any.
+    pub fn finish_chars(mut self) -> Result<Vec<char>, BaseHanError> {
+        if self.nbytes == 0 {
+            return Err(BaseHanError::EmptyInput);
+        }
+        Ok(self.finish_in_place())
+    }
+
+    /// Return the chars not drained yet (from `write` calls), followed by the terminator in
+    /// the output form and the HMAC tag if any. An empty stream encodes to nothing.
+    pub fn into_chars(mut self) -> Vec<char> {
+        let mut out = core::mem::take(&mut self.buf_out);
+        out.extend(self.finish_in_place());
+        out
+    }
+
+    /// Call `f` with each char `into_chars` would return, rather than collecting them.
+    pub fn finish_with_callback<F: FnMut(char)>(self, f: F) {
+        self.into_chars().into_iter().for_each(f);
+    }
+
+    /// Call `f` with each output char as soon as it is produced, rather than returning them,
+    /// see `CallbackBaseHanEncoder`.
+    pub fn with_callback<F: FnMut(char)>(mut self, f: F) -> CallbackBaseHanEncoder<F> {
+        self.buf_out.shrink_to(0); // Never holds more than the chars of one byte
+        CallbackBaseHanEncoder { encoder: self, f }
+    }
+
+    /// Like `finish`, but an empty input is not an error and encodes to nothing.
+    pub(crate) fn finish_or_empty(self) -> Result<Option<char>, BaseHanError> {
+        match self.finish() {
+            Ok(c) => Ok(Some(c)),
+            Err(BaseHanError::EmptyInput) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Dump the remaining bits out in the output form, followed by the HMAC tag if any,
+    /// leaving the encoder ready for a new stream. An empty stream encodes to nothing.
+    pub(crate) fn finish_in_place(&mut self) -> Vec<char> {
+        self.column = 0;
+        self.nchars = 0;
+        let nbytes = core::mem::take(&mut self.nbytes);
+        let remainings = core::mem::take(&mut self.remainings);
+        let nbits = remainings.nbits();
+        let mut out = Vec::new();
+        if nbytes == 0 {
+            return out;
+        }
+        push_encoded(&mut out, terminator(remainings.dump(), nbits), self.url_safe);
+        #[cfg(feature = "hmac")]
+        if let Some(mac) = &mut self.hmac {
+            let mut cache = BitCache13::default();
+            for byte in integrity::take_tag(mac) {
+                if let Some(index) = cache.fill(byte) {
+                    push_encoded(&mut out, self.alphabet.char_at(index), self.url_safe);
+                }
+            }
+        }
+        out
+    }
+
+    /// Like `fill_buf_out`, but with the `simd` feature, split the whole 13-byte blocks in bulk
+    /// unless the output is wrapped or percent-encoded.
+    fn fill_buf_out_slice(&mut self, chunk: &[u8]) {
+        #[cfg(feature = "simd")]
+        if !self.url_safe && self.line_width == 0 {
+            // One byte at a time until the header is out and the cache is empty, 13 bytes at most
+            let mut chunk = chunk;
+            while let Some((&byte, rest)) = chunk.split_first() {
+                if self.nbytes > 0 && self.remainings.nbits() == 0 {
+                    break;
+                }
+                self.fill_buf_out([byte]);
+                chunk = rest;
+            }
+            let (blocks, tail) = chunk.split_at(chunk.len() / simd::BLOCK_SIZE * simd::BLOCK_SIZE);
+            let ngroups = blocks.len() / simd::BLOCK_SIZE * 8;
+            self.buf_out.reserve(ngroups);
+            let (alphabet, buf_out) = (&self.alphabet, &mut self.buf_out);
+            simd::for_each_group(blocks, |index| buf_out.push(alphabet.char_at(index)));
+            self.nbytes += blocks.len();
+            self.nchars += ngroups;
+            self.column += ngroups;
+            #[cfg(feature = "hmac")]
+            if let Some(mac) = &mut self.hmac {
+                hmac::Mac::update(mac, blocks);
+            }
+            self.fill_buf_out(tail.iter().copied());
+            return;
+        }
+        self.fill_buf_out(chunk.iter().copied());
+    }
+
+    fn fill_buf_out<I>(&mut self, buf_in: I)
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        for byte in buf_in {
+            if self.header && self.nbytes == 0 {
+                push_encoded(&mut self.buf_out, VERSION_PREFIX, self.url_safe);
+            }
+            self.nbytes += 1;
+            #[cfg(feature = "hmac")]
+            if let Some(mac) = &mut self.hmac {
+                hmac::Mac::update(mac, &[byte]);
+            }
+            if let Some(index) = self.remainings.fill(byte) {
+                let out = self.alphabet.char_at(index);
+                push_encoded(&mut self.buf_out, out, self.url_safe);
+                self.nchars += 1;
+                self.column += 1;
+                // The terminator always follows, so the separator never trails the output
+                if self.column == self.line_width {
+                    self.buf_out.extend(self.line_separator.chars());
+                    self.column = 0;
+                }
+            }
+        }
+    }
+}
+
+/// Encode the UTF-8 bytes of `s` in one shot, terminator included.
+pub fn encode_str(s: &str) -> Result<String, BaseHanError> {
+    BaseHanEncoder::encode_bytes_to_string(s.as_bytes())
+}
+
+/// Encode the native representation of `s`, e.g. a file path, in one shot, terminator included:
+/// the raw bytes on Unix, the UTF-16 code units as little-endian bytes on Windows.
+/// Decode it with `decode_to_os_string` on the same platform.
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub fn encode_os_str(s: &std::ffi::OsStr) -> Result<String, BaseHanError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        BaseHanEncoder::encode_bytes_to_string(s.as_bytes())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        let mut encoder = BaseHanEncoder::new();
+        let mut out = String::from_iter(encoder.update_iter(s.encode_wide().flat_map(u16::to_le_bytes))?);
+        out.extend(encoder.finish_or_empty()?);
+        Ok(out)
+    }
+}
+
+/// Decode a string encoded by `encode_os_str`.
+/// On Windows, return `OddLength` if the bytes do not make UTF-16 code units.
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub fn decode_to_os_string(encoded: &str) -> Result<std::ffi::OsString, BaseHanError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        BaseHanDecoder::decode_str_to_bytes(encoded).map(std::ffi::OsString::from_vec)
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        BaseHanDecoder::decode_to_u16_le(encoded).map(|wide| std::ffi::OsString::from_wide(&wide))
+    }
+}
+
+/// Decode `input` in one shot like `String::from_utf8_lossy`, dropping every char the decoder
+/// rejects, e.g. a stray char or an invalid terminator, rather than failing, and stopping at the
+/// terminator. The bits of a truncated stream that do not make a whole byte are lost.
+/// With the `log` feature, warn about each dropped char.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub fn decode_lossy(input: &str) -> Vec<u8> {
+    let mut decoder = BaseHanDecoder::with_buffer_size(0);
+    let mut out = Vec::with_capacity(input.len() / 3 * 2 + 1); // 3 UTF-8 bytes per char, 2 bytes at most
+    for (i, c) in input.chars().enumerate() {
+        // One char at a time, so that an error only drops the char that caused it
+        match decoder.decode_into(&[c], &mut out) {
+            Err(BaseHanError::EndOfFile) => break,
+            #[cfg(feature = "log")]
+            Err(e) => log::warn!("skipping {:?} ({:#x}) at pos {}: {}", c, c as u32, i, e),
+            _ => (),
+        }
+        if decoder.is_finished() {
+            break;
+        }
+    }
+    out
+}
+
+/// Encode `input` in one shot as escaped code points, e.g. `\u{5B0C}\u{6E03}`, for debugging.
+pub fn encode_debug(input: &[u8]) -> Result<String, BaseHanError> {
+    use core::fmt::Write as _;
+
+    let encoded = BaseHanEncoder::encode_bytes_to_string(input)?;
+    let mut out = String::with_capacity(encoded.chars().count() * 8);
+    for c in encoded.chars() {
+        write!(out, "\\u{{{:04X}}}", c as u32).expect("Writing to a String never fails");
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "std")]
+impl Write for BaseHanEncoder {
+    /// Encode the bytes into the internal buffer, drain it with `encoded_chars`.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fill_buf_out_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Dump the remaining bits out and append the terminator to the internal buffer.
+    /// Bytes written afterwards start a new stream.
+    fn flush(&mut self) -> io::Result<()> {
+        let tail = self.finish_in_place();
+        self.buf_out.extend(tail);
+        Ok(())
+    }
+}
+
+/// Settings of a `BaseHanEncoder`, to combine rather than picking one of its constructors.
+#[derive(Clone)]
+pub struct BaseHanEncoderBuilder {
+    buffer_size: usize,
+    alphabet: Alphabet,
+    line_width: usize,
+    line_separator: &'static str,
+    url_safe: bool,
+    header: bool,
+    chunk_size: usize,
+    #[cfg(feature = "hmac")]
+    hmac: Option<HmacSha256>,
+}
+
+impl Default for BaseHanEncoderBuilder {
+    fn default() -> Self {
+        BaseHanEncoderBuilder::new()
+    }
+}
+
+impl BaseHanEncoderBuilder {
+    /// Start from the settings of `BaseHanEncoder::new`.
+    pub fn new() -> Self {
+        BaseHanEncoderBuilder {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            alphabet: Alphabet::Offset(BASE_OFFSET),
+            line_width: 0,
+            line_separator: "\n",
+            url_safe: false,
+            header: false,
+            chunk_size: DEFAULT_BUFFER_SIZE,
+            #[cfg(feature = "hmac")]
+            hmac: None,
+        }
+    }
+
+    /// See `BaseHanEncoder::with_buffer_size`.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// See `BaseHanEncoder::with_offset`, checked by `build`.
+    pub fn base_offset(mut self, base_offset: u32) -> Self {
+        self.alphabet = Alphabet::Offset(base_offset);
+        self
+    }
+
+    /// See `BaseHanEncoder::with_alphabet`, checked by `build`.
+    pub fn alphabet(mut self, alphabet: &'static [char; 8192]) -> Self {
+        self.alphabet = Alphabet::Custom(alphabet);
+        self
+    }
+
+    /// See `BaseHanEncoder::with_line_width`.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// See `BaseHanEncoder::line_separator`.
+    pub fn line_separator(mut self, line_separator: &'static str) -> Self {
+        self.line_separator = line_separator;
+        self
+    }
+
+    /// See `BaseHanEncoder::url_safe`.
+    pub fn url_safe(mut self, url_safe: bool) -> Self {
+        self.url_safe = url_safe;
+        self
+    }
+
+    /// See `BaseHanEncoder::with_header`.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// See `BaseHanEncoder::with_chunk_size`.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// See `BaseHanEncoder::with_hmac`.
+    #[cfg(feature = "hmac")]
+    pub fn hmac(mut self, key: &[u8]) -> Self {
+        self.hmac = Some(integrity::new_mac(key));
+        self
+    }
+
+    /// Return `InvalidOffset` or `InvalidAlphabet` as the corresponding constructors do.
+    pub fn build(self) -> Result<BaseHanEncoder, BaseHanError> {
+        match self.alphabet {
+            Alphabet::Offset(base_offset) => validate_offset(base_offset)?,
+            Alphabet::Custom(alphabet) => validate_alphabet(alphabet)?,
+        }
+        Ok(self.assemble())
+    }
+
+    /// Build without checking the alphabet, for the constructors which keep the default one.
+    fn assemble(self) -> BaseHanEncoder {
+        BaseHanEncoder {
+            buf_out: Vec::with_capacity(self.buffer_size),
+            remainings: BitCache13::default(),
+            alphabet: self.alphabet,
+            line_width: self.line_width,
+            line_separator: self.line_separator,
+            column: 0,
+            nchars: 0,
+            nbytes: 0,
+            url_safe: self.url_safe,
+            header: self.header,
+            chunk_size: self.chunk_size,
+            #[cfg(feature = "hmac")]
+            hmac: self.hmac,
+        }
+    }
+}
+
+/// A `BaseHanEncoder` bound to a source of bytes, see `BaseHanEncoder::from_reader`.
+#[cfg(feature = "std")]
+pub struct ReaderBaseHanEncoder<R> {
+    reader: R,
+    encoder: BaseHanEncoder,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> ReaderBaseHanEncoder<R> {
+    /// Read `chunk_size` bytes at a time in `encode_all`, `DEFAULT_BUFFER_SIZE` by default.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.encoder = self.encoder.with_chunk_size(chunk_size);
+        self
+    }
+
+    /// Read until EOF and return the encoding, terminator included.
+    /// Empty input encodes to an empty string.
+    pub fn encode_all(self) -> Result<String, BaseHanError> {
+        let mut out = Vec::new();
+        self.encoder.encode_file(self.reader, &mut out)?;
+        Ok(String::from_utf8(out).expect("Encoded chars are always valid UTF-8"))
+    }
+}
+
+/// A `BaseHanEncoder` accumulating its output into a `String`, rather than returning
+/// a `Vec<char>` for each chunk.
+#[derive(Clone)]
+pub struct StringBaseHanEncoder {
+    encoder: BaseHanEncoder,
+    out: String,
+}
+
+impl Default for StringBaseHanEncoder {
+    fn default() -> Self {
+        StringBaseHanEncoder::new()
+    }
+}
+
+impl From<BaseHanEncoder> for StringBaseHanEncoder {
+    /// Keep the settings of `encoder`, e.g. line wrapping or url-safe output.
+    fn from(encoder: BaseHanEncoder) -> Self {
+        StringBaseHanEncoder {
+            encoder,
+            out: String::new(),
+        }
+    }
+}
+
+impl StringBaseHanEncoder {
+    pub fn new() -> Self {
+        StringBaseHanEncoder::from(BaseHanEncoder::new())
+    }
+
+    pub fn update<T>(&mut self, chunk: T)
+    where
+        T: AsRef<[u8]>,
+    {
+        self.encoder.fill_buf_out_slice(chunk.as_ref());
+        // Keep the char buffer of the encoder allocated for the next chunk
+        self.out.extend(self.encoder.buf_out.drain(..));
+    }
+
+    /// Take the output accumulated so far.
+    pub fn take_string(&mut self) -> String {
+        core::mem::take(&mut self.out)
+    }
+
+    /// Append the terminator and return the whole remaining output.
+    /// An empty stream encodes to an empty string.
+    pub fn finish_into_string(mut self) -> String {
+        self.out.extend(self.encoder.finish_in_place());
+        self.out
+    }
+}
+
+/// A `BaseHanEncoder` handing each output char to a callback, e.g. to write it to a serial port,
+/// rather than accumulating the chars of a whole chunk, see `BaseHanEncoder::with_callback`.
+pub struct CallbackBaseHanEncoder<F> {
+    encoder: BaseHanEncoder,
+    f: F,
+}
+
+impl<F: FnMut(char)> CallbackBaseHanEncoder<F> {
+    pub fn update<T>(&mut self, chunk: T)
+    where
+        T: AsRef<[u8]>,
+    {
+        for &byte in chunk.as_ref() {
+            self.encoder.fill_buf_out(core::iter::once(byte));
+            self.encoder.buf_out.drain(..).for_each(&mut self.f);
+        }
+    }
+
+    /// Call the callback with the terminator, and the HMAC tag if any.
+    /// An empty stream encodes to nothing.
+    pub fn finish(self) {
+        self.encoder.finish_with_callback(self.f);
+    }
+}
+
+/// An encoder for real-time use, one byte at a time, keeping the chars in a ring buffer of fixed
+/// capacity, so that nothing is allocated after `new`. Only the default alphabet is supported.
+#[derive(Clone)]
+pub struct RingBufferBaseHanEncoder {
+    ring: VecDeque<char>,
+    capacity: usize,
+    remainings: BitCache13,
+    nbytes: usize,
+}
+
+impl RingBufferBaseHanEncoder {
+    pub fn new(capacity: usize) -> Self {
+        RingBufferBaseHanEncoder {
+            ring: VecDeque::with_capacity(capacity),
+            capacity,
+            remainings: BitCache13::default(),
+            nbytes: 0,
+        }
+    }
+
+    /// Encode `byte`, return the char it completes, if any, which is also pushed to the ring.
+    /// Return `BufferTooSmall` without consuming `byte` if the ring is full and `byte` would complete a char.
+    pub fn try_encode(&mut self, byte: u8) -> Result<Option<char>, BaseHanError> {
+        // 5 bits or more and a byte make a 13-bit group
+        if self.remainings.nbits() >= 5 && self.ring.len() == self.capacity {
+            return Err(BaseHanError::BufferTooSmall(self.capacity + 1));
+        }
+        self.nbytes += 1;
+        let out = self.remainings.fill(byte).map(|index| Alphabet::Offset(BASE_OFFSET).char_at(index));
+        self.ring.extend(out);
+        Ok(out)
+    }
+
+    /// Pop the oldest char out of the ring.
+    pub fn pop(&mut self) -> Option<char> {
+        self.ring.pop_front()
+    }
+
+    /// Number of chars in the ring.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
 
-pub struct BaseHanEncoder {
-    buf_out: Vec<char>,
-    remainings: BitCache13,
-}
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 
-impl BaseHanEncoder {
-    pub fn new() -> Self {
-        BaseHanEncoder {
-            buf_out: Vec::with_capacity(DEFAULT_BUFFER_SIZE),
-            remainings: BitCache13::default(),
+    /// Dump the remaining bits out as the terminator, leaving the encoder ready for a new stream.
+    /// The terminator is returned, not pushed to the ring.
+    /// Return `EmptyInput` if no byte has been fed, in which case there is nothing to terminate.
+    pub fn finish(&mut self) -> Result<char, BaseHanError> {
+        if core::mem::take(&mut self.nbytes) == 0 {
+            return Err(BaseHanError::EmptyInput);
         }
+        let remainings = core::mem::take(&mut self.remainings);
+        let nbits = remainings.nbits();
+        Ok(terminator(remainings.dump(), nbits))
     }
+}
 
-    pub fn with_buffer_size(buffer_size: usize) -> Self {
-        BaseHanEncoder {
-            buf_out: Vec::with_capacity(buffer_size),
-            remainings: BitCache13::default(),
-        }
+/// Encoded output usable like a `str`, e.g. with `format!` or `println!`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BaseHanString(String);
+
+impl BaseHanString {
+    /// Number of encoded chars, terminator included, line separators and padding excluded.
+    pub fn char_count(&self) -> usize {
+        self.0.chars().filter(|&c| !is_skipped(c)).count()
     }
 
-    pub fn update<T>(&mut self, chunk: T) -> Result<Vec<char>, BaseHanError>
-    where
-        T: AsRef<[u8]>,
-    {
-        let buf_in = chunk.as_ref();
+    /// Number of bits carried by the chars: 13 for each data char, and only the remaining bits
+    /// for the terminator, not its leading 1. The version prefix carries none.
+    /// A complete stream carries 8 times as many bits as it has decoded bytes.
+    pub fn bit_length(&self) -> usize {
+        self.0
+            .chars()
+            .filter(|&c| !is_skipped(c))
+            .map(|c| match c as u32 {
+                code if (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code) => {
+                    terminator_bits(code - ENDING_OFFSET).map_or(0, |(_, nbits)| nbits)
+                }
+                code if (VERSION_OFFSET..VERSION_OFFSET + 0x10).contains(&code) => 0,
+                _ => CODE_RANGE.trailing_zeros() as usize,
+            })
+            .sum()
+    }
 
-        for &byte in buf_in {
-            if let Some(out) = self.remainings.fill(byte) {
-                self.buf_out.push(out);
-            }
-        }
+    /// The UTF-8 bytes of the encoded chars, like `str::as_bytes`.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
 
-        let buf_out = std::mem::replace(&mut self.buf_out, Vec::new()); // Replace buffer with new & return the taken value
-        return Ok(buf_out);
+    /// Convert into the UTF-8 bytes of the encoded chars without copying, like `String::into_bytes`.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0.into_bytes()
     }
+}
 
-    /// Dump the remaining bits out.
-    pub fn finish(self) -> char {
-        self.remainings.dump()
+impl fmt::Display for BaseHanString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
-#[derive(Default)]
-struct BitCache13 {
-    inner: u32,
-    nbits: usize,
+impl core::ops::Deref for BaseHanString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
 }
 
-impl BitCache13 {
-    /// Fill one byte at a time, if full(13 bits), return char and pop it.
-    /// Otherwise, return none.
+impl AsRef<str> for BaseHanString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
 
-    pub(crate) fn fill(&mut self, byte: u8) -> Option<char> {
-        let remain_bits = (self.nbits + 8) % 13;
-        let out = match self.nbits {
-            0..=4 => { // Not full, return none
-                self.inner <<= 8;
-                self.inner |= byte as u32;
-                None
-            }
-            5..=12 => {
-                self.inner <<= 8;
-                self.inner |= byte as u32;
-                let output_char_u32 = self.inner >> ((self.nbits + 8) % 13);
-                self.inner = self.inner & ((1 << remain_bits) - 1); // head padding nums overflows in u8, and then appended to the buffer
-                let output_char = char::from_u32(output_char_u32 + BASE_OFFSET)
-                    .expect("Data cannot convert to a valid char, which should never happen.");
-                Some(output_char)
-            }
-            13.. =>
-                panic!("Remaining bits overflow! This should never happen!")
-        };
-        self.nbits = (self.nbits + 8) % 13;
-        return out;
+impl From<Vec<char>> for BaseHanString {
+    fn from(chars: Vec<char>) -> Self {
+        BaseHanString(String::from_iter(chars))
     }
+}
 
-    /// Dump remaining bits to a char ranging from 0x6e00 to 0x7e00, indicating the end of stream.
-    /// Since the inner is left aligned, it needs to be aligned right in this case.
-    /// Otherwise, hint: 1000 0000 0000 can refer to 1 or 10 or 100 or and so on.
-    pub(crate) fn dump(self) -> char {
-        let out_char_u32 = self.inner + ENDING_OFFSET;
-        let out_char = char::from_u32(out_char_u32)
-            .expect("Data cannot convert to a valid char, which should never happen.");
-        return out_char;
+impl From<String> for BaseHanString {
+    fn from(s: String) -> Self {
+        BaseHanString(s)
     }
 }
 
+// Fail to compile rather than silently lose thread safety, e.g. through an `Rc` field.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BaseHanEncoder>();
+    assert_send_sync::<BaseHanDecoder>();
+};
+
+#[derive(Clone)]
 pub struct BaseHanDecoder {
     buf_out: Vec<u8>,
     remainings: BitCache8,
     eof: bool,
+    percent: PercentDecoder,
+    alphabet: Alphabet,
+    chunk_size: usize, // For decode_file
+    lenient: bool,
+    header: bool,
+    started: bool, // Whether a char other than whitespace or padding has been decoded
+    #[cfg(feature = "hmac")]
+    hmac: Option<Verifier>,
+    error: Option<BaseHanError>, // The first error returned by `update`
+    skip: usize, // Decoded bytes left to drop
+    consumed: usize, // Input chars processed, for `chars_consumed`
+    multi_message: bool,
+    messages: Vec<Vec<u8>>, // Completed messages in multi-message mode
+}
+
+/// Where a `BaseHanDecoder` is in its stream, see `BaseHanDecoder::state`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecoderState {
+    /// Waiting for more chars, up to the terminator.
+    Streaming,
+    /// The terminator (and the HMAC tag if verifying) has been decoded.
+    Finished,
+    /// An update failed with this error, the decoded bytes cannot be trusted anymore.
+    Error(BaseHanError),
+}
+
+impl Default for BaseHanDecoder {
+    fn default() -> Self {
+        BaseHanDecoder::new()
+    }
 }
 
 impl BaseHanDecoder {
     pub fn new() -> Self {
-        BaseHanDecoder {
-            buf_out: Vec::with_capacity(DEFAULT_BUFFER_SIZE),
-            remainings: BitCache8::default(),
-            eof: false,
-        }
+        BaseHanDecoder::with_buffer_size(DEFAULT_BUFFER_SIZE)
     }
 
     pub fn with_buffer_size(buffer_size: usize) -> Self {
@@ -121,99 +1519,888 @@ impl BaseHanDecoder {
             buf_out: Vec::with_capacity(buffer_size),
             remainings: BitCache8::default(),
             eof: false,
+            percent: PercentDecoder::default(),
+            alphabet: Alphabet::Offset(BASE_OFFSET),
+            chunk_size: DEFAULT_BUFFER_SIZE,
+            lenient: false,
+            header: false,
+            started: false,
+            #[cfg(feature = "hmac")]
+            hmac: None,
+            error: None,
+            skip: 0,
+            consumed: 0,
+            multi_message: false,
+            messages: Vec::new(),
         }
     }
 
+    /// Decode chars from `[base_offset, base_offset + 0x2000)`, see `BaseHanEncoder::with_offset`.
+    pub fn with_offset(base_offset: u32) -> Result<Self, BaseHanError> {
+        validate_offset(base_offset)?;
+        let mut decoder = BaseHanDecoder::new();
+        decoder.alphabet = Alphabet::Offset(base_offset);
+        Ok(decoder)
+    }
+
+    /// Decode chars of a custom alphabet, see `BaseHanEncoder::with_alphabet`.
+    pub fn with_alphabet(alphabet: &'static [char; 8192]) -> Result<Self, BaseHanError> {
+        validate_alphabet(alphabet)?;
+        let mut decoder = BaseHanDecoder::new();
+        decoder.alphabet = Alphabet::Custom(alphabet);
+        Ok(decoder)
+    }
+
+    /// Expect the HMAC tag of `BaseHanEncoder::with_hmac` after the terminator, and fail with
+    /// `IntegrityFailure` once it is decoded if it does not match. Decoded bytes are handed out
+    /// before, so do not trust them until `finish` succeeds.
+    #[cfg(feature = "hmac")]
+    pub fn with_hmac_verify(mut self, key: &[u8]) -> Self {
+        self.hmac = Some(Verifier::new(key));
+        self
+    }
+
+    /// Skip the `VERSION_PREFIX` header of `BaseHanEncoder::with_header` at the start of the stream,
+    /// if any.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Decode concatenated messages, starting over after each terminator (and HMAC tag, and
+    /// header, if any) rather than failing with `EndOfFile`. Take the messages with `update_messages`;
+    /// the other decoding methods only hand out the bytes of the message in progress.
+    pub fn with_multi_message(mut self, multi_message: bool) -> Self {
+        self.multi_message = multi_message;
+        self
+    }
+
+    /// Silently ignore every char outside both the encoding and the terminator range,
+    /// e.g. CJK punctuation copied along with the text, instead of failing with `InvalidCode`.
+    /// That includes a '%' not followed by a well-formed percent-encoded char.
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Decode the chars of `chunk`, e.g. a `Vec<char>`, a `&[char]` or `s.chars()`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(level = "trace")))]
     pub fn update<T>(&mut self, chunk: T) -> Result<Vec<u8>, BaseHanError>
     where
-        T: AsRef<[char]>,
+        T: IntoIterator,
+        T::Item: Borrow<char>,
     {
-        if self.eof {
-            return Err(BaseHanError::EndOfFile);
+        #[cfg(feature = "tracing")]
+        let consumed = self.consumed;
+        self.fill_buf_out(chunk.into_iter().map(|c| *c.borrow()))?;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(chars = self.consumed - consumed, bytes = self.buf_out.len());
+
+        let buf_out = core::mem::take(&mut self.buf_out); // Replace buffer with new & return the taken value
+        Ok(buf_out)
+    }
+
+    /// Decode the chars of `chunk` in multi-message mode, see `with_multi_message`, and return
+    /// the messages completed, which may be none. The message in progress stays pending.
+    pub fn update_messages<T>(&mut self, chunk: T) -> Result<Vec<Vec<u8>>, BaseHanError>
+    where
+        T: IntoIterator,
+        T::Item: Borrow<char>,
+    {
+        self.fill_buf_out(chunk.into_iter().map(|c| *c.borrow()))?;
+        Ok(core::mem::take(&mut self.messages))
+    }
+
+    /// Decode the chars of `s` directly, without collecting them first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(level = "trace")))]
+    pub fn update_str(&mut self, s: &str) -> Result<Vec<u8>, BaseHanError> {
+        #[cfg(feature = "tracing")]
+        let consumed = self.consumed;
+        self.fill_buf_out(s.chars())?;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(chars = self.consumed - consumed, bytes = self.buf_out.len());
+
+        let buf_out = core::mem::take(&mut self.buf_out);
+        Ok(buf_out)
+    }
+
+    /// Decode chars pulled from an iterator, e.g. `line.chars()` for each line of a `BufRead`,
+    /// without collecting them first.
+    pub fn update_from_str_iter<I>(&mut self, iter: I) -> Result<Vec<u8>, BaseHanError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.fill_buf_out(iter)?;
+
+        let buf_out = core::mem::take(&mut self.buf_out);
+        Ok(buf_out)
+    }
+
+    /// Like `update`, but copy the bytes into `out` rather than returning a new `Vec`.
+    /// Return the number of bytes written, or `BufferTooSmall` with the size needed if they do not
+    /// fit, in which case they stay pending: call again with a larger `out` and no more chars.
+    pub fn decode_into_slice(&mut self, chars: &[char], out: &mut [u8]) -> Result<usize, BaseHanError> {
+        self.fill_buf_out(chars.iter().copied())?;
+        let n = self.buf_out.len();
+        let Some(out) = out.get_mut(..n) else {
+            return Err(BaseHanError::BufferTooSmall(n));
+        };
+        out.copy_from_slice(&self.buf_out);
+        self.buf_out.clear(); // The buffer stays allocated for the next chunk
+        Ok(n)
+    }
+
+    /// Like `update`, but write the bytes to `writer` rather than returning a new `Vec`.
+    /// Return the number of bytes written.
+    #[cfg(feature = "std")]
+    pub fn decode_into_writer<I, W>(&mut self, chars: I, mut writer: W) -> Result<usize, BaseHanError>
+    where
+        I: IntoIterator<Item = char>,
+        W: Write,
+    {
+        self.fill_buf_out(chars)?;
+        let n = self.buf_out.len();
+        let result = writer.write_all(&self.buf_out).map_err(BaseHanError::IoError);
+        self.buf_out.clear(); // The buffer stays allocated for the next chunk
+        result.map(|()| n)
+    }
+
+    /// Forward the decoded bytes of every chunk to `sink`, so that no more than one chunk is
+    /// held in memory, see `BaseHanDecoderWithSink`.
+    #[cfg(feature = "std")]
+    pub fn with_sink<W: Write>(self, sink: W) -> BaseHanDecoderWithSink<W> {
+        BaseHanDecoderWithSink { decoder: self, sink }
+    }
+
+    /// Like `update`, but append the bytes to `out` rather than returning a new `Vec`.
+    /// Return the number of bytes appended.
+    pub fn decode_into(&mut self, chunk: &[char], out: &mut Vec<u8>) -> Result<usize, BaseHanError> {
+        self.fill_buf_out(chunk.iter().copied())?;
+        let n = self.buf_out.len();
+        out.append(&mut self.buf_out); // The buffer stays allocated for the next chunk
+        Ok(n)
+    }
+
+    fn fill_buf_out<I>(&mut self, buf_in: I) -> Result<(), BaseHanError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let start = self.buf_out.len();
+        let result = self.decode_chars(buf_in);
+        let start = start.min(self.buf_out.len()); // Completed messages are moved out
+        match &result {
+            Ok(()) => {
+                let n = self.skip.min(self.buf_out.len() - start);
+                self.buf_out.drain(start..start + n);
+                self.skip -= n;
+            }
+            Err(e) => {
+                self.error.get_or_insert_with(|| e.clone());
+            }
         }
-        let buf_in = chunk.as_ref();
+        result
+    }
 
-        for &c in buf_in {
-            let mut c = c as u32;
-            if c == 0 {break};
-            self.eof = c >= ENDING_OFFSET;
-            if self.eof {
-                c -= ENDING_OFFSET;
-                c <<= self.remainings.nbits + 5;
+    fn decode_chars<I>(&mut self, buf_in: I) -> Result<(), BaseHanError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let finished_before = self.eof;
+        #[cfg(feature = "hmac")]
+        let mut verified = self.buf_out.len(); // Decoded bytes already fed to the verifier
+
+        for (i, c) in buf_in.into_iter().enumerate() {
+            self.consumed += 1;
+            let c = if self.percent.accepts(c) {
+                match self.percent.push(c) {
+                    Ok(Some(c)) => c,
+                    Ok(None) => continue,
+                    Err(()) if self.lenient => {
+                        // A stray '%' is text like any other: drop it, and take `c` afresh
+                        self.percent = PercentDecoder::default();
+                        if c == '%' {
+                            let _ = self.percent.push(c);
+                            continue;
+                        }
+                        c
+                    }
+                    Err(()) => {
+                        self.percent = PercentDecoder::default();
+                        self.buf_out.clear();
+                        return Err(BaseHanError::InvalidCode(c as u32, i));
+                    }
+                }
             } else {
-                c -= BASE_OFFSET;
+                c
+            };
+            let code = c as u32;
+            let is_terminator = (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code);
+            if is_skipped(c) {
+                continue;
+            }
+            let is_data = self.alphabet.index_of(c).is_some();
+            if self.header && !self.started && c == VERSION_PREFIX && !is_data {
+                self.started = true;
+                continue;
+            }
+            if self.lenient && !is_terminator && !is_data {
+                continue;
+            }
+            self.started = true;
+            if self.eof {
+                // The HMAC tag follows the terminator
+                #[cfg(feature = "hmac")]
+                if let Some(verifier) = self.hmac.as_mut().filter(|v| !v.is_complete()) {
+                    let Some(index) = self.alphabet.index_of(c) else {
+                        self.buf_out.clear();
+                        return Err(BaseHanError::InvalidCode(code, i));
+                    };
+                    if verifier.push(index) {
+                        verifier.update(&self.buf_out[verified..]);
+                        verified = self.buf_out.len();
+                        if !verifier.verify() {
+                            self.buf_out.clear();
+                            return Err(BaseHanError::IntegrityFailure);
+                        }
+                        if self.multi_message {
+                            self.end_message();
+                            verified = 0;
+                        }
+                    }
+                    continue;
+                }
+                // Padding may arrive after the terminator, but nothing else
+                if finished_before {
+                    return Err(BaseHanError::EndOfFile);
+                }
+                self.consumed -= 1; // Left to the caller
+                break;
             }
-            match self.remainings.fill(c) {
-                Single(byte) => {
+            let out = if is_terminator {
+                let Some((bits, nbits)) = terminator_bits(code - ENDING_OFFSET) else {
+                    self.buf_out.clear();
+                    return Err(BaseHanError::InvalidCode(code, i));
+                };
+                // The encoder dumps whatever completes the last byte, no more, no less
+                if !(self.remainings.nbits() + nbits).is_multiple_of(8) {
+                    self.buf_out.clear();
+                    return Err(BaseHanError::InvalidTerminator(code, i));
+                }
+                self.eof = true;
+                self.remainings.fill_tail(bits, nbits)
+            } else if let Some(index) = self.alphabet.index_of(c) {
+                Some(self.remainings.fill(index))
+            } else {
+                // Only the terminator ends the stream, anything else out of range is corrupted input
+                self.buf_out.clear();
+                return Err(self.alphabet.out_of_range(code, i));
+            };
+            match out {
+                Some(Single(byte)) => {
                     self.buf_out.push(byte);
                 }
-                Double(bytes) => {
+                Some(Double(bytes)) => {
                     self.buf_out.extend_from_slice(&bytes);
                 }
+                None => (),
             }
-            if self.eof {
-                break;
+            if self.multi_message && self.is_finished() {
+                self.end_message();
+                #[cfg(feature = "hmac")]
+                {
+                    verified = 0;
+                }
+            }
+        }
+        #[cfg(feature = "hmac")]
+        if let Some(verifier) = &mut self.hmac {
+            verifier.update(&self.buf_out[verified..]);
+        }
+        Ok(())
+    }
+
+    /// Move the message just completed out and start over, for multi-message mode.
+    fn end_message(&mut self) {
+        self.messages.push(core::mem::take(&mut self.buf_out));
+        self.remainings = BitCache8::default();
+        self.eof = false;
+        self.started = false;
+        #[cfg(feature = "hmac")]
+        if let Some(verifier) = &mut self.hmac {
+            verifier.reset();
+        }
+    }
+
+    /// Whether the stream is going on, finished, or failed, without feeding more chars.
+    pub fn state(&self) -> DecoderState {
+        match &self.error {
+            Some(e) => DecoderState::Error(e.clone()),
+            None if self.is_finished() => DecoderState::Finished,
+            None => DecoderState::Streaming,
+        }
+    }
+
+    /// Drop any pending output, remaining bits and error, keeping the settings and the allocated
+    /// buffer, so that the decoder can be reused for another stream.
+    pub fn reset(&mut self) -> &mut Self {
+        self.buf_out.clear();
+        self.remainings = BitCache8::default();
+        self.eof = false;
+        self.percent = PercentDecoder::default();
+        self.started = false;
+        #[cfg(feature = "hmac")]
+        if let Some(verifier) = &mut self.hmac {
+            verifier.reset();
+        }
+        self.error = None;
+        self.skip = 0;
+        self.consumed = 0;
+        self.messages.clear();
+        self
+    }
+
+    /// Number of input chars processed since the start of the stream, including line separators,
+    /// padding and the terminator. Once finished, the chars following the stream in the last
+    /// chunk are not counted, so that the decoder can serve as a sub-parser.
+    pub fn chars_consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Estimate how many bytes the rest of a stream of `total_chars` chars decodes to, for
+    /// progress reporting: 0 once finished or if empty, otherwise an upper bound, the most bytes
+    /// the bits left in the cache and the remaining chars (the last one being the terminator) can make.
+    /// The `VERSION_PREFIX` header still expected with `with_header` and the HMAC tag expected
+    /// with `with_hmac_verify` are not counted as data chars.
+    /// The terminator carries whatever completes the last byte, which the char count alone cannot tell
+    /// apart from 8 bits more when both fit in its 12 bits, e.g. 13 and 14 bytes both encode to 8 data chars.
+    /// The estimate is then one byte over for the shorter stream, and it is over as well if the rest
+    /// contains line separators or padding, which are counted as data chars.
+    /// Return `None` if fewer than `total_chars` chars are left for the rest of the stream.
+    pub fn chars_remaining_estimate(&self, total_chars: usize) -> Option<usize> {
+        if self.eof || total_chars == 0 {
+            return Some(0); // Empty input decodes to nothing, nor does the HMAC tag
+        }
+        // Chars left which are no data: the header still expected, and the HMAC tag
+        let overhead = usize::from(self.header && !self.started);
+        #[cfg(feature = "hmac")]
+        let overhead = overhead + self.hmac.as_ref().map_or(0, |_| integrity::TAG_CHARS);
+        let data_chars = total_chars.checked_sub(self.consumed + overhead)?.checked_sub(1)?;
+        let bits = self.remainings.nbits() + data_chars * CODE_RANGE.trailing_zeros() as usize;
+        // The fewest terminator bits completing the last byte, or 8 more if they still fit
+        let tail = (8 - bits % 8) % 8;
+        let tail = if tail + 8 < CODE_RANGE.trailing_zeros() as usize { tail + 8 } else { tail };
+        Some((bits + tail) / 8)
+    }
+
+    /// Drop the next `n` decoded bytes instead of returning them, e.g. to jump past a header,
+    /// starting with those pending from `feed`.
+    /// Return the error the decoder has stopped at if any, or `EndOfFile` if the stream has ended
+    /// with fewer than `n` bytes pending.
+    pub fn skip(&mut self, n: usize) -> Result<(), BaseHanError> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let pending = n.min(self.buf_out.len());
+        if n > pending && self.eof {
+            return Err(BaseHanError::EndOfFile);
+        }
+        self.buf_out.drain(..pending);
+        self.skip += n - pending;
+        Ok(())
+    }
+
+    /// Number of decoded bytes not handed out yet, i.e. left by `feed` and `take_bytes`.
+    /// The other decoding methods hand out everything pending; bits not making a whole byte
+    /// are not counted.
+    pub fn pending_bytes(&self) -> usize {
+        self.buf_out.len()
+    }
+
+    /// Decode the chars of `chunk`, keeping the bytes for `take_bytes` (or the next `update`)
+    /// rather than returning them. Return the number of bytes pending.
+    pub fn feed<T>(&mut self, chunk: T) -> Result<usize, BaseHanError>
+    where
+        T: IntoIterator,
+        T::Item: Borrow<char>,
+    {
+        self.fill_buf_out(chunk.into_iter().map(|c| *c.borrow()))?;
+        Ok(self.buf_out.len())
+    }
+
+    /// Take exactly the first `n` pending bytes, e.g. a message of known length,
+    /// or return `None` if fewer are pending, in which case more input must be fed.
+    pub fn take_bytes(&mut self, n: usize) -> Option<Vec<u8>> {
+        if self.buf_out.len() < n {
+            return None;
+        }
+        let rest = self.buf_out.split_off(n);
+        Some(core::mem::replace(&mut self.buf_out, rest))
+    }
+
+    /// Whether the terminator, and the HMAC tag if verifying, have been decoded.
+    pub fn is_finished(&self) -> bool {
+        #[cfg(feature = "hmac")]
+        if self.hmac.as_ref().is_some_and(|v| !v.is_complete()) {
+            return false;
+        }
+        self.eof
+    }
+
+    /// Return the byte left in the bit cache, if any, or `TruncatedStream` if the terminator
+    /// (or the HMAC tag) has not been decoded, in which case the decoded bytes are likely incomplete.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, err(level = "trace")))]
+    pub fn finish(self) -> Result<Option<u8>, BaseHanError> {
+        if !self.is_finished() {
+            return Err(BaseHanError::TruncatedStream);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(total_chars = self.consumed);
+        Ok(self.remainings.dump())
+    }
+
+    /// Read `chunk_size` bytes at a time in `decode_file`, `DEFAULT_BUFFER_SIZE` by default.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Decode UTF-8 Base-Han text from `input` into `output`, until the terminator.
+    /// Empty input decodes to nothing, as does a lone terminator without remaining bits (U+6E01).
+    /// Return the number of bytes read.
+    #[cfg(feature = "std")]
+    pub fn decode_file<R, W>(mut self, mut input: R, mut output: W) -> Result<u64, BaseHanError>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut buf = vec![0u8; self.chunk_size.max(1)]; // An empty buffer would read as EOF
+        let mut pending = Vec::new(); // Bytes of a char split by the chunk boundary
+        let mut total = 0u64;
+        while !self.is_finished() {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(BaseHanError::IoError(e)),
+            };
+            total += n as u64;
+            pending.extend_from_slice(&buf[..n]);
+            let chars = take_utf8_chars(&mut pending)?;
+            let out = self.update(chars)?;
+            output.write_all(&out).map_err(BaseHanError::IoError)?;
+        }
+        if !pending.is_empty() {
+            return Err(invalid_data("the string input is corrupted"));
+        }
+        match self.finish() {
+            Ok(None) => (),
+            Ok(Some(_)) => return Err(invalid_data("the string input is corrupted")),
+            Err(BaseHanError::TruncatedStream) if total == 0 => (), // Empty input encodes to nothing
+            Err(e) => return Err(e),
+        }
+        output.flush().map_err(BaseHanError::IoError)?;
+        Ok(total)
+    }
+
+    /// Decode every line of `reader` as a complete message, terminated at the end of the line.
+    /// An empty line is an empty message, and anything after the terminator is an `EndOfFile` error.
+    #[cfg(feature = "std")]
+    pub fn from_buf_read<R>(reader: R) -> impl Iterator<Item = Result<Vec<u8>, BaseHanError>>
+    where
+        R: BufRead,
+    {
+        reader.lines().map(|line| {
+            let line = line.map_err(BaseHanError::IoError)?;
+            BaseHanDecoder::decode_message(&line)
+        })
+    }
+
+    /// Split `input` on `delimiter` and decode each segment as a complete message,
+    /// see `BaseHanEncoder::encode_chunked`.
+    pub fn decode_chunked(input: &str, delimiter: char) -> Result<Vec<Vec<u8>>, BaseHanError> {
+        input.split(delimiter).map(BaseHanDecoder::decode_message).collect()
+    }
+
+    /// Decode a complete message, which must end with the terminator unless empty,
+    /// with nothing but whitespace or padding after it.
+    fn decode_message(input: &str) -> Result<Vec<u8>, BaseHanError> {
+        BaseHanDecoder::decode_str_to_bytes(input)
+    }
+
+    /// Split a message from `BaseHanEncoder::encode_with_metadata` back into `(data, meta)`.
+    /// Return `TruncatedStream` if the message is incomplete, `EndOfFile` if anything follows it,
+    /// `InvalidRange` if the metadata length runs past the end.
+    pub fn decode_with_metadata(input: &str) -> Result<(Vec<u8>, Vec<u8>), BaseHanError> {
+        let mut bytes = BaseHanDecoder::decode_message(input)?;
+        let (len, rest) = bytes
+            .split_first_chunk::<LEN_PREFIX_BYTES>()
+            .ok_or(BaseHanError::InvalidRange)?;
+        let meta_len = u32::from_be_bytes(*len) as usize;
+        if meta_len > rest.len() {
+            return Err(BaseHanError::InvalidRange);
+        }
+        let data = bytes.split_off(LEN_PREFIX_BYTES + meta_len);
+        bytes.drain(..LEN_PREFIX_BYTES);
+        Ok((data, bytes))
+    }
+
+    /// Decode a message from `BaseHanEncoder::encode_length_prefixed` and return the data only.
+    /// Return `TruncatedStream` if the message is incomplete, `EndOfFile` if anything follows it,
+    /// `InvalidRange` if the length prefix is missing or does not match the length of the data.
+    pub fn decode_length_prefixed(input: &str) -> Result<Vec<u8>, BaseHanError> {
+        let mut bytes = BaseHanDecoder::decode_message(input)?;
+        let (len, rest) = bytes
+            .split_first_chunk::<LEN_PREFIX_BYTES>()
+            .ok_or(BaseHanError::InvalidRange)?;
+        if u32::from_be_bytes(*len) as usize != rest.len() {
+            return Err(BaseHanError::InvalidRange);
+        }
+        bytes.drain(..LEN_PREFIX_BYTES);
+        Ok(bytes)
+    }
+
+    /// Decode the output of `BaseHanEncoder::encode_boolean_array`, of any number of bits.
+    /// Return `TruncatedStream` if the terminator is missing and `EndOfFile` if anything follows it.
+    pub fn decode_boolean_array(input: &str) -> Result<Vec<bool>, BaseHanError> {
+        let code_bits = CODE_RANGE.trailing_zeros() as usize;
+        let unpack = |value: u32, nbits: usize| (0..nbits).rev().map(move |i| value >> i & 1 == 1);
+        let mut out = Vec::with_capacity(input.len() / 3 * code_bits); // 3 UTF-8 bytes per char
+        let mut chars = input.chars().enumerate().filter(|&(_, c)| !is_skipped(c));
+        for (i, c) in chars.by_ref() {
+            let code = c as u32;
+            if (BASE_OFFSET..ENDING_OFFSET).contains(&code) {
+                out.extend(unpack(code - BASE_OFFSET, code_bits));
+            } else if (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code) {
+                let (value, nbits) =
+                    terminator_bits(code - ENDING_OFFSET).ok_or(BaseHanError::InvalidCode(code, i))?;
+                out.extend(unpack(value, nbits));
+                return match chars.next() {
+                    Some(_) => Err(BaseHanError::EndOfFile),
+                    None => Ok(out),
+                };
+            } else {
+                return Err(Alphabet::Offset(BASE_OFFSET).out_of_range(code, i));
+            }
+        }
+        if !out.is_empty() {
+            return Err(BaseHanError::TruncatedStream);
+        }
+        Ok(out)
+    }
+
+    /// Decode a complete Base-Han string in one shot, reading the bytes as little-endian 16-bit values.
+    pub fn decode_to_u16_le(input: &str) -> Result<Vec<u16>, BaseHanError> {
+        let bytes = BaseHanDecoder::decode_str_to_bytes(input)?;
+        to_u16s(&bytes, u16::from_le_bytes)
+    }
+
+    /// Decode a complete Base-Han string in one shot, reading the bytes as big-endian 16-bit values.
+    pub fn decode_to_u16_be(input: &str) -> Result<Vec<u16>, BaseHanError> {
+        let bytes = BaseHanDecoder::decode_str_to_bytes(input)?;
+        to_u16s(&bytes, u16::from_be_bytes)
+    }
+
+    /// Check that every char of `input` is a data char of this decoder's alphabet, a terminator,
+    /// whitespace or padding, without decoding. Percent-encoded input is not accepted.
+    /// In lenient mode every char outside both ranges passes, as `update` would skip it.
+    /// Return `CodeOutOfRange` with the position of the first invalid char,
+    /// or `InvalidCode` for U+6E00, which is in range but no terminator.
+    pub fn validate(&self, input: &str) -> Result<(), BaseHanError> {
+        for (i, c) in input.chars().enumerate() {
+            let code = c as u32;
+            if is_skipped(c) || self.alphabet.index_of(c).is_some() {
+                continue;
+            }
+            if (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code) {
+                terminator_bits(code - ENDING_OFFSET).ok_or(BaseHanError::InvalidCode(code, i))?;
+            } else if !self.lenient {
+                return Err(self.alphabet.out_of_range(code, i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode a complete Base-Han string in one shot, with the version its prefix tells,
+    /// or as v1 if it has none. Return `InvalidCode` at pos 0 for an unknown version.
+    pub fn auto_detect(input: &str) -> Result<Vec<u8>, BaseHanError> {
+        let trimmed = input.trim_start_matches(is_skipped);
+        let mut chars = trimmed.chars();
+        match chars.next() {
+            Some(VERSION_PREFIX) => BaseHanDecoder::decode_str_to_bytes(chars.as_str()),
+            Some(crate::basehan::v2::VERSION_PREFIX) => {
+                crate::basehan::v2::BaseHanDecoder::decode_str_to_bytes(chars.as_str())
             }
+            Some(c) if (VERSION_OFFSET..VERSION_OFFSET + 0x10).contains(&(c as u32)) => {
+                Err(BaseHanError::InvalidCode(c as u32, 0))
+            }
+            _ => BaseHanDecoder::decode_str_to_bytes(input),
         }
+    }
 
-        let buf_out = std::mem::replace(&mut self.buf_out, Vec::new()); // Replace buffer with new & return the taken value
-        return Ok(buf_out);
+    /// Decode a complete Base-Han string in one shot.
+    /// Return `TruncatedStream` if it does not end with the terminator (unless empty),
+    /// and `EndOfFile` if anything but whitespace or padding follows the terminator.
+    pub fn decode_str_to_bytes(input: &str) -> Result<Vec<u8>, BaseHanError> {
+        // Each char is at least 3 bytes in UTF-8 and decodes to at most 2 bytes
+        let mut decoder = BaseHanDecoder::with_buffer_size(input.len() / 3 * 2 + 1);
+        let out = decoder.update_str(input)?;
+        decoder.finish_str(input)?;
+        Ok(out)
     }
 
-    pub fn finish(self) -> Option<u8> {
-        self.remainings.dump()
+    /// Check that `input`, just decoded by `update_str`, is a whole stream: terminated unless
+    /// empty, with nothing but whitespace or padding left after the terminator.
+    fn finish_str(self, input: &str) -> Result<(), BaseHanError> {
+        // The decoder stops right before the first char following the stream
+        if input.chars().skip(self.consumed).any(|c| !is_skipped(c)) {
+            return Err(BaseHanError::EndOfFile);
+        }
+        match self.finish() {
+            Ok(None) => Ok(()),
+            Ok(Some(_)) => Err(BaseHanError::EndOfFile),
+            Err(BaseHanError::TruncatedStream) if input.chars().all(is_skipped) => Ok(()),
+            Err(e) => Err(e),
+        }
     }
 }
 
-#[derive(Debug)]
-enum BitCache8Out {
-    Single(u8),
-    Double([u8; 2]),
-}
-
-#[derive(Default)]
-struct BitCache8 {
-    inner: u32,
-    nbits: usize,
-}
-
-
-impl BitCache8 {
-    /// Fill 13 bits at a time. The remaining bits are left-aligned (the same as BitCache13)
-    /// Return one byte or 2 bytes
-    pub(crate) fn fill(&mut self, bits: u32) -> BitCache8Out {
-        let remain_bits = (self.nbits + 13) % 8;
-        let out = match self.nbits {
-            0..=2 => {
-                self.inner <<= 13;
-                self.inner |= bits;
-                let out_byte = (self.inner >> remain_bits) as u8;
-                self.inner = self.inner & ((1 << remain_bits) - 1);
-                Single(out_byte)
-            }
-            3.. => {
-                self.inner <<= 13;
-                self.inner |= bits;
-                let out_byte_1 = (self.inner >> (remain_bits + 8)) as u8;
-                let out_byte_2 =(self.inner >> remain_bits) as u8;
-                self.inner = self.inner & ((1 << remain_bits) - 1);
-                Double([out_byte_1, out_byte_2])
+fn to_u16s(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<Vec<u16>, BaseHanError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(BaseHanError::OddLength(bytes.len()));
+    }
+    Ok(bytes.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect())
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(msg: &str) -> BaseHanError {
+    BaseHanError::IoError(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Take the chars decoded from the valid UTF-8 prefix of `pending`,
+/// leaving the bytes of a char split by a chunk boundary in it.
+#[cfg(feature = "std")]
+pub(crate) fn take_utf8_chars(pending: &mut Vec<u8>) -> Result<Vec<char>, BaseHanError> {
+    let valid_len = match core::str::from_utf8(pending) {
+        Ok(s) => s.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => return Err(invalid_data("stream did not contain valid UTF-8")),
+    };
+    let chars = String::from_utf8_lossy(&pending[..valid_len]).chars().collect();
+    pending.drain(..valid_len);
+    Ok(chars)
+}
+
+/// The error of `io` interfaces reaching the end of their input before the terminator.
+#[cfg(feature = "std")]
+pub(crate) fn truncated_stream() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, BaseHanError::TruncatedStream.to_string())
+}
+
+/// Surface a `BaseHanError` through `io` interfaces.
+#[cfg(feature = "std")]
+pub(crate) fn into_io_error(e: BaseHanError) -> io::Error {
+    match e {
+        BaseHanError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// A `BaseHanDecoder` writing its output to a sink, e.g. a `BufWriter<File>`, rather than
+/// returning a `Vec<u8>` for each chunk, see `BaseHanDecoder::with_sink`.
+#[cfg(feature = "std")]
+pub struct BaseHanDecoderWithSink<W> {
+    decoder: BaseHanDecoder,
+    sink: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> BaseHanDecoderWithSink<W> {
+    /// Decode the chars of `chunk` into the sink and return the number of bytes written.
+    pub fn update<T>(&mut self, chunk: T) -> Result<usize, BaseHanError>
+    where
+        T: IntoIterator,
+        T::Item: Borrow<char>,
+    {
+        self.decoder.decode_into_writer(chunk.into_iter().map(|c| *c.borrow()), &mut self.sink)
+    }
+
+    /// Decode the chars of `s` into the sink and return the number of bytes written.
+    pub fn update_str(&mut self, s: &str) -> Result<usize, BaseHanError> {
+        self.decoder.decode_into_writer(s.chars(), &mut self.sink)
+    }
+
+    /// Whether the terminator, and the HMAC tag if verifying, have been decoded.
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Check that the stream is complete like `BaseHanDecoder::finish`, flush the sink and return it.
+    /// Empty input decodes to nothing.
+    pub fn finish(mut self) -> Result<W, BaseHanError> {
+        let empty = self.decoder.chars_consumed() == 0;
+        match self.decoder.finish() {
+            Ok(None) => (),
+            Ok(Some(_)) => return Err(invalid_data("the string input is corrupted")),
+            Err(BaseHanError::TruncatedStream) if empty => (),
+            Err(e) => return Err(e),
+        }
+        self.sink.flush().map_err(BaseHanError::IoError)?;
+        Ok(self.sink)
+    }
+}
+
+/// Decode chars pulled from `source` on demand, so that Base-Han data can be consumed through `Read`.
+/// Reading fails with `UnexpectedEof` if `source` runs out before the terminator.
+#[cfg(feature = "std")]
+pub struct BaseHanReader<I> {
+    source: I,
+    decoder: BaseHanDecoder,
+    buf_out: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "std")]
+impl<I> BaseHanReader<I>
+where
+    I: Iterator<Item = char>,
+{
+    pub fn new<S>(source: S) -> Self
+    where
+        S: IntoIterator<IntoIter = I>,
+    {
+        BaseHanReader {
+            source: source.into_iter(),
+            decoder: BaseHanDecoder::new(),
+            buf_out: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<&'a str> for BaseHanReader<core::str::Chars<'a>> {
+    fn from(source: &'a str) -> Self {
+        BaseHanReader::new(source.chars())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<&'a [char]> for BaseHanReader<core::iter::Copied<core::slice::Iter<'a, char>>> {
+    fn from(source: &'a [char]) -> Self {
+        BaseHanReader::new(source.iter().copied())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I> Read for BaseHanReader<I>
+where
+    I: Iterator<Item = char>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Decode another chunk only when all decoded bytes have been handed out
+        while self.pos == self.buf_out.len() {
+            if self.decoder.is_finished() {
+                return Ok(0);
             }
-        };
-        self.nbits = (self.nbits + 13) % 8;
-        return out;
+            let chunk: Vec<char> = self.source.by_ref().take(buf.len().max(1)).collect();
+            if chunk.is_empty() {
+                // A stream is not over until its terminator, unless empty
+                if self.decoder.chars_consumed() > 0 {
+                    return Err(truncated_stream());
+                }
+                return Ok(0);
+            }
+            self.buf_out = self
+                .decoder
+                .update(chunk)
+                .map_err(into_io_error)?;
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.buf_out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf_out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// An encoder and a decoder sharing the same settings, to check that data survives a round trip.
+pub struct BaseHanCodec {
+    encoder: BaseHanEncoder,
+    decoder: BaseHanDecoder,
+}
+
+impl Default for BaseHanCodec {
+    fn default() -> Self {
+        BaseHanCodec::new()
     }
+}
 
-    /// Dump the remaining byte out.
-    /// Typically, this is expected to return none when reaching the last character.
-    pub(crate) fn dump(self) -> Option<u8> {
-        if self.inner != 0 {
-            return Some(self.inner as u8);
+impl BaseHanCodec {
+    pub fn new() -> Self {
+        BaseHanCodec {
+            encoder: BaseHanEncoder::new(),
+            decoder: BaseHanDecoder::new(),
         }
-        None
+    }
+
+    pub fn with_offset(base_offset: u32) -> Result<Self, BaseHanError> {
+        Ok(BaseHanCodec {
+            encoder: BaseHanEncoder::with_offset(base_offset)?,
+            decoder: BaseHanDecoder::with_offset(base_offset)?,
+        })
+    }
+
+    pub fn with_alphabet(alphabet: &'static [char; 8192]) -> Result<Self, BaseHanError> {
+        Ok(BaseHanCodec {
+            encoder: BaseHanEncoder::with_alphabet(alphabet)?,
+            decoder: BaseHanDecoder::with_alphabet(alphabet)?,
+        })
+    }
+
+    pub fn encoder(&mut self) -> &mut BaseHanEncoder {
+        &mut self.encoder
+    }
+
+    pub fn decoder(&mut self) -> &mut BaseHanDecoder {
+        &mut self.decoder
+    }
+
+    /// Encode `data` then decode it back from a clean state, return whether the result equals `data`.
+    pub fn round_trip(&mut self, data: &[u8]) -> Result<bool, BaseHanError> {
+        self.encoder.reset();
+        self.decoder.reset();
+
+        let mut encoded = self.encoder.update(data)?;
+        encoded.extend(self.encoder.finish_in_place());
+        let decoded = self.decoder.update(encoded)?;
+        Ok(decoded == data && (data.is_empty() || self.decoder.is_finished()))
     }
 }
 
+/// Raw bytes alongside their encoding, e.g. for test fixtures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseHanPair {
+    pub raw: Vec<u8>,
+    pub encoded: String,
+}
 
+impl BaseHanPair {
+    pub fn from_raw(raw: Vec<u8>) -> Result<Self, BaseHanError> {
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&raw)?;
+        Ok(BaseHanPair { raw, encoded })
+    }
 
+    /// Return `TruncatedStream` if `encoded` is not a complete message.
+    pub fn from_encoded(encoded: &str) -> Result<Self, BaseHanError> {
+        let raw = BaseHanDecoder::decode_message(encoded)?;
+        Ok(BaseHanPair {
+            raw,
+            encoded: String::from(encoded),
+        })
+    }
 
+    /// Whether `encoded` is a complete message decoding to `raw`.
+    pub fn verify(&self) -> bool {
+        BaseHanDecoder::decode_message(&self.encoded).is_ok_and(|raw| raw == self.raw)
+    }
+}
@@ -0,0 +1,262 @@
+//! Base-Han v2, packing 14 bits per char into `[0x5000, 0x9000)`.
+//!
+//! 2^14 data chars leave too few ideographs for a one-char terminator carrying up to 13 bits,
+//! so the stream ends with a marker from `[0x9000, 0x900E)` telling how many bits remain,
+//! followed by one more data char holding them unless there are none.
+//! The marker is out of the v1 range and v1 data below 0x5000 is out of the v2 range,
+//! so a decoder of the wrong version usually fails with `CodeOutOfRange`, or `TruncatedStream`
+//! on `finish`, rather than silently decoding.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+pub use crate::basehan::v1::BaseHanError;
+use crate::basehan::v1::is_skipped;
+
+/// Optionally prepended to the output to tell the version, see `v1::BaseHanDecoder::auto_detect`.
+pub const VERSION_PREFIX: char = '\u{9f02}';
+
+const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024; // 1 MiB
+pub const BASE_OFFSET_V2: u32 = 0x5000;
+const CODE_BITS: usize = 14;
+const CODE_RANGE: u32 = 1 << CODE_BITS; // 2^14 code points, one for each 14-bit group
+pub(crate) const ENDING_OFFSET: u32 = BASE_OFFSET_V2 + CODE_RANGE;
+pub(crate) const ENDING_RANGE: u32 = CODE_BITS as u32; // 0 to 13 remaining bits
+
+fn code(value: u32) -> char {
+    char::from_u32(BASE_OFFSET_V2 + value)
+        .expect("Data cannot convert to a valid char, which should never happen.")
+}
+
+fn marker(nbits: usize) -> char {
+    char::from_u32(ENDING_OFFSET + nbits as u32)
+        .expect("Data cannot convert to a valid char, which should never happen.")
+}
+
+#[derive(Clone)]
+pub struct BaseHanEncoder {
+    buf_out: Vec<char>,
+    remainings: u32,
+    nbits: usize,
+    nbytes: usize,
+}
+
+impl Default for BaseHanEncoder {
+    fn default() -> Self {
+        BaseHanEncoder::new()
+    }
+}
+
+impl BaseHanEncoder {
+    pub fn new() -> Self {
+        BaseHanEncoder::with_buffer_size(DEFAULT_BUFFER_SIZE)
+    }
+
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        BaseHanEncoder {
+            buf_out: Vec::with_capacity(buffer_size),
+            remainings: 0,
+            nbits: 0,
+            nbytes: 0,
+        }
+    }
+
+    pub fn update<T>(&mut self, chunk: T) -> Result<Vec<char>, BaseHanError>
+    where
+        T: AsRef<[u8]>,
+    {
+        for &byte in chunk.as_ref() {
+            self.nbytes += 1;
+            self.remainings = self.remainings << 8 | byte as u32;
+            self.nbits += 8;
+            if self.nbits >= CODE_BITS {
+                self.nbits -= CODE_BITS;
+                self.buf_out.push(code(self.remainings >> self.nbits));
+                self.remainings &= (1 << self.nbits) - 1;
+            }
+        }
+        Ok(core::mem::take(&mut self.buf_out))
+    }
+
+    /// Encode the UTF-8 bytes of `s`.
+    pub fn update_str(&mut self, s: &str) -> Result<Vec<char>, BaseHanError> {
+        self.update(s.as_bytes())
+    }
+
+    /// Dump the remaining bits out behind the end marker, 1 or 2 chars.
+    /// Return `EmptyInput` if no byte has been fed, in which case there is nothing to terminate.
+    #[must_use = "dropping the encoder without calling finish produces an incomplete encoding"]
+    pub fn finish(self) -> Result<Vec<char>, BaseHanError> {
+        if self.nbytes == 0 {
+            return Err(BaseHanError::EmptyInput);
+        }
+        let mut out = Vec::with_capacity(2);
+        out.push(marker(self.nbits));
+        if self.nbits > 0 {
+            out.push(code(self.remainings));
+        }
+        Ok(out)
+    }
+
+    /// Encode the whole input in one shot, end marker included.
+    /// Empty input encodes to an empty string.
+    pub fn encode_bytes_to_string(input: &[u8]) -> Result<String, BaseHanError> {
+        if input.is_empty() {
+            return Ok(String::new());
+        }
+        let mut encoder = BaseHanEncoder::with_buffer_size(input.len() * 8 / CODE_BITS + 2);
+        let mut out = String::from_iter(encoder.update(input)?);
+        out.extend(encoder.finish()?);
+        Ok(out)
+    }
+
+    /// Like `encode_bytes_to_string`, behind `VERSION_PREFIX`.
+    pub fn encode_bytes_to_prefixed_string(input: &[u8]) -> Result<String, BaseHanError> {
+        let mut out = String::from(VERSION_PREFIX);
+        out += &BaseHanEncoder::encode_bytes_to_string(input)?;
+        Ok(out)
+    }
+}
+
+#[derive(Clone)]
+pub struct BaseHanDecoder {
+    buf_out: Vec<u8>,
+    remainings: u32,
+    nbits: usize,
+    tail_bits: Option<usize>, // Set by the end marker, until the last data char arrives
+    eof: bool,
+    consumed: usize, // Input chars processed, up to the end of the stream
+}
+
+impl Default for BaseHanDecoder {
+    fn default() -> Self {
+        BaseHanDecoder::new()
+    }
+}
+
+impl BaseHanDecoder {
+    pub fn new() -> Self {
+        BaseHanDecoder::with_buffer_size(DEFAULT_BUFFER_SIZE)
+    }
+
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        BaseHanDecoder {
+            buf_out: Vec::with_capacity(buffer_size),
+            remainings: 0,
+            nbits: 0,
+            tail_bits: None,
+            eof: false,
+            consumed: 0,
+        }
+    }
+
+    /// Decode the chars of `chunk`, e.g. a `Vec<char>`, a `&[char]` or `s.chars()`.
+    pub fn update<T>(&mut self, chunk: T) -> Result<Vec<u8>, BaseHanError>
+    where
+        T: IntoIterator,
+        T::Item: Borrow<char>,
+    {
+        self.fill_buf_out(chunk.into_iter().map(|c| *c.borrow()))?;
+        Ok(core::mem::take(&mut self.buf_out))
+    }
+
+    /// Decode the chars of `s` directly, without collecting them first.
+    pub fn update_str(&mut self, s: &str) -> Result<Vec<u8>, BaseHanError> {
+        self.fill_buf_out(s.chars())?;
+        Ok(core::mem::take(&mut self.buf_out))
+    }
+
+    fn fill_buf_out<I>(&mut self, buf_in: I) -> Result<(), BaseHanError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let finished_before = self.eof;
+
+        for (i, c) in buf_in.into_iter().enumerate() {
+            self.consumed += 1;
+            if is_skipped(c) {
+                continue;
+            }
+            if self.eof {
+                // Padding may arrive after the end, but nothing else
+                if finished_before {
+                    return Err(BaseHanError::EndOfFile);
+                }
+                self.consumed -= 1; // Left to the caller
+                break;
+            }
+            let code = c as u32;
+            let value = code.wrapping_sub(BASE_OFFSET_V2);
+            if let Some(nbits) = self.tail_bits {
+                // The last data char holds exactly `nbits` bits
+                if value >> nbits != 0 {
+                    self.buf_out.clear();
+                    return Err(BaseHanError::InvalidCode(code, i));
+                }
+                self.push_bits(value, nbits);
+                self.tail_bits = None;
+                self.eof = true;
+            } else if value < CODE_RANGE {
+                self.push_bits(value, CODE_BITS);
+            } else if (ENDING_OFFSET..ENDING_OFFSET + ENDING_RANGE).contains(&code) {
+                match code - ENDING_OFFSET {
+                    0 => self.eof = true,
+                    nbits => self.tail_bits = Some(nbits as usize),
+                }
+            } else {
+                self.buf_out.clear();
+                return Err(BaseHanError::CodeOutOfRange {
+                    code,
+                    pos: i,
+                    valid_min: BASE_OFFSET_V2,
+                    valid_max: ENDING_OFFSET + ENDING_RANGE - 1,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn push_bits(&mut self, bits: u32, nbits: usize) {
+        self.remainings = self.remainings << nbits | bits;
+        self.nbits += nbits;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.buf_out.push((self.remainings >> self.nbits) as u8);
+        }
+        self.remainings &= (1 << self.nbits) - 1;
+    }
+
+    /// Whether the end marker and the last data char have been decoded.
+    pub fn is_finished(&self) -> bool {
+        self.eof
+    }
+
+    /// Return the byte left in the bit cache, if any, or `TruncatedStream` if the stream
+    /// has not ended, in which case the decoded bytes are likely incomplete.
+    pub fn finish(self) -> Result<Option<u8>, BaseHanError> {
+        if !self.eof {
+            return Err(BaseHanError::TruncatedStream);
+        }
+        Ok((self.remainings != 0).then_some(self.remainings as u8))
+    }
+
+    /// Decode a complete Base-Han v2 string in one shot.
+    /// Return `TruncatedStream` if it does not end with the end marker (and the last data char),
+    /// unless empty, and `EndOfFile` if anything but whitespace or padding follows.
+    pub fn decode_str_to_bytes(input: &str) -> Result<Vec<u8>, BaseHanError> {
+        // Each char is 3 bytes in UTF-8 and decodes to at most 2 bytes
+        let mut decoder = BaseHanDecoder::with_buffer_size(input.len() / 3 * 2 + 1);
+        let out = decoder.update_str(input)?;
+        // The decoder stops right before the first char following the stream
+        if input.chars().skip(decoder.consumed).any(|c| !is_skipped(c)) {
+            return Err(BaseHanError::EndOfFile);
+        }
+        match decoder.finish() {
+            Ok(None) => Ok(out),
+            Ok(Some(_)) => Err(BaseHanError::EndOfFile),
+            Err(BaseHanError::TruncatedStream) if input.chars().all(is_skipped) => Ok(out),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! JavaScript bindings of the v1 codec, built with `wasm-pack build -- --features wasm`.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+
+/// Encode `data` to a Base-Han string.
+#[wasm_bindgen]
+pub fn encode(data: &[u8]) -> String {
+    BaseHanEncoder::encode_bytes_to_string(data).expect("Encoding in memory never fails")
+}
+
+/// Decode a Base-Han string, throwing the error message on invalid input.
+#[wasm_bindgen]
+pub fn decode(s: &str) -> Result<Vec<u8>, JsValue> {
+    BaseHanDecoder::decode_str_to_bytes(s).map_err(|e| JsValue::from_str(&e.to_string()))
+}
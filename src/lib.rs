@@ -1,2 +1,60 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod basehan;
-pub use basehan::*;
\ No newline at end of file
+pub use basehan::*;
+pub use basehan::v1::ENDING_OFFSET;
+
+/// Number of chars the v1 encoder produces for `byte_count` bytes, terminator included and
+/// line separators excluded. Empty input encodes to an empty string.
+pub const fn encoded_char_count(byte_count: usize) -> usize {
+    if byte_count == 0 {
+        return 0;
+    }
+    // floor(byte_count * 8 / 13) full chars, the remaining bits (< 13) go into the terminator.
+    byte_count / 13 * 8 + byte_count % 13 * 8 / 13 + 1
+}
+
+/// Maximum number of bytes `char_count` v1 chars (terminator included) can decode to,
+/// saturating at `usize::MAX`.
+pub fn decoded_byte_count_upper_bound(char_count: usize) -> usize {
+    if char_count == 0 {
+        return 0;
+    }
+    // 13 bits per full char, plus at most 12 bits carried by the terminator.
+    let nfull = char_count - 1;
+    (nfull / 8)
+        .saturating_mul(13)
+        .saturating_add((nfull % 8 * 13 + 12) / 8)
+}
+
+/// Infer the version of an encoded string: from its version prefix if any, otherwise from the
+/// range of its chars. v1 chars are all below 0x8E00 with a terminator from 0x6E00, and
+/// chars below 0x5000 are v1 only, while every complete v2 stream has an end marker from 0x9000.
+/// Return `None` for chars of neither version, when a truncated stream cannot be told apart,
+/// or when chars follow the v1 terminator of what cannot be a v2 stream.
+pub fn detect_version(input: &str) -> Option<u8> {
+    use basehan::{v1, v2};
+
+    let mut chars = input.chars().filter(|&c| !v1::is_skipped(c)).peekable();
+    match chars.peek() {
+        Some(&v1::VERSION_PREFIX) => return Some(1),
+        Some(&v2::VERSION_PREFIX) => return Some(2),
+        _ => (),
+    }
+    let mut terminated = false; // Whether a v1 terminator has been seen
+    let mut trailing = false; // Whether anything follows it, so that it is no v1 stream
+    for c in chars {
+        trailing |= terminated;
+        match c as u32 {
+            code if code < basehan::BASE_OFFSET => return None,
+            code if code < v2::BASE_OFFSET_V2 => return (!trailing).then_some(1),
+            code if code < v1::ENDING_OFFSET => (),
+            code if code < v1::ENDING_OFFSET + v1::ENDING_RANGE => terminated = true,
+            code if code < v2::ENDING_OFFSET + v2::ENDING_RANGE => return Some(2),
+            _ => return None,
+        }
+    }
+    (terminated && !trailing).then_some(1)
+}
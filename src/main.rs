@@ -1,12 +1,15 @@
 use std::{
+    fs::File,
     io::{self, Read, stdin, Write},
+    path::PathBuf,
     process::exit,
 };
 
 use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
 
 use basehan::BaseHanError;
-use basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+use basehan::v1::{self, BaseHanDecoder, BaseHanEncoder};
 
 // Base-Han is a command line tool to encode/decode binary data to/from Base-Han.
 #[derive(Debug, Parser)]
@@ -17,8 +20,28 @@ struct Args {
     decode: bool,
     #[clap(short, long, default_value = "false")]
     interactive: bool,
-    #[clap(short, long, default_value = "3145728")]
+    #[clap(short, long, default_value = "3145728", value_parser = validate_chunk_size)]
     chunk_size: usize,
+    // Show the bytes read, throughput and ETA on stderr
+    #[clap(short, long, default_value = "false")]
+    progress: bool,
+    // Read from this file instead of stdin, long-only as -i is --interactive
+    #[clap(long)]
+    input: Option<PathBuf>,
+    // Write to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+// 13 bytes make 8 complete characters, anything smaller is pointless.
+const MIN_CHUNK_SIZE: usize = 13;
+
+fn validate_chunk_size(s: &str) -> Result<usize, String> {
+    let chunk_size: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if chunk_size < MIN_CHUNK_SIZE {
+        return Err(format!("must be at least {} bytes", MIN_CHUNK_SIZE));
+    }
+    Ok(chunk_size)
 }
 
 const ENCODE_PROMPT: &str = "encode> ";
@@ -46,64 +69,110 @@ fn interactive_shell(decode: bool) {
             break;
         }
         if decode {
-            let result = basehan::decode(&buffer.to_string());
-            match result {
+            match BaseHanDecoder::decode_str_to_bytes(buffer) {
                 Ok(bytes) => {
                     io::stdout().write_all(&bytes).unwrap();
                     println!();
                 }
-                Err(e) => {
-                    println!("Error: Internal error.{:?}", e);
-                }
+                Err(e) => println!("Error: Please input a valid BaseHan cipher. {}", e),
             }
         } else {
-            let result = basehan::encode(buffer);
-            match result {
+            match encode_line(buffer) {
                 Ok(result) => println!("{}", result),
-                Err(err) => println!("Error: Please input a valid BaseHan cipher.{:?}", err),
+                Err(e) => println!("Error: {}", e),
             }
         }
     }
     println!("Exit");
 }
 
+// Each line is a complete stream, encoded with a fresh encoder
+fn encode_line(line: &str) -> Result<String, v1::BaseHanError> {
+    let mut encoder = BaseHanEncoder::new();
+    let mut result = String::from_iter(encoder.update_str(line)?);
+    match encoder.finish() {
+        Ok(terminator) => result.push(terminator),
+        Err(v1::BaseHanError::EmptyInput) => (),
+        Err(e) => return Err(e),
+    }
+    Ok(result)
+}
+
+// Size of stdin when redirected from a file, for the ETA
+#[cfg(unix)]
+fn stdin_len() -> Option<u64> {
+    use std::os::fd::AsFd;
+
+    let file = File::from(io::stdin().as_fd().try_clone_to_owned().ok()?);
+    file_len(&file)
+}
+
+#[cfg(not(unix))]
+fn stdin_len() -> Option<u64> {
+    None
+}
+
+fn file_len(file: &File) -> Option<u64> {
+    let metadata = file.metadata().ok()?;
+    metadata.is_file().then_some(metadata.len())
+}
+
+fn progress_bar(len: Option<u64>) -> ProgressBar {
+    let (bar, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{wide_bar} {bytes}/{total_bytes} {binary_bytes_per_sec} ETA {eta}",
+        ),
+        None => (ProgressBar::new_spinner(), "{spinner} {bytes} {binary_bytes_per_sec}"),
+    };
+    bar.set_style(ProgressStyle::with_template(template).expect("Invalid progress bar template"));
+    bar
+}
+
+fn open_or_exit(path: &PathBuf, open: fn(&PathBuf) -> io::Result<File>) -> File {
+    open(path).unwrap_or_else(|e| {
+        eprintln!("Error: cannot open {}: {}", path.display(), e);
+        exit(1);
+    })
+}
 
 fn v1(args: Args) {
-    if args.decode {
-        let mut buf = vec![0u8; args.chunk_size];
-        let mut decoder = BaseHanDecoder::new();
-        loop {
-            buf.fill(0);
-            let n = io::stdin().read(&mut buf).unwrap();
-
-            if n == 0 {
-                if let Some(_) = decoder.finish() {
-                    panic!("The string input is corrupted!")
-                }
-                break;
-            }
-            let char_buf: Vec<char> = String::from_utf8_lossy(&buf).chars().collect();
-            let out = decoder.update(char_buf).unwrap();
-            io::stdout().write_all(&out).unwrap();
-            io::stdout().flush().unwrap();
-        }
+    if args.interactive {
+        return interactive_shell(args.decode);
+    }
+
+    let input = args.input.as_ref().map(|path| open_or_exit(path, |path| File::open(path)));
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(open_or_exit(path, |path| File::create(path))),
+        None => Box::new(io::stdout().lock()),
+    };
+    let bar = args
+        .progress
+        .then(|| progress_bar(input.as_ref().map_or_else(stdin_len, file_len)));
+    let input: Box<dyn Read> = match input {
+        Some(file) => Box::new(file),
+        None => Box::new(io::stdin().lock()),
+    };
+    let input: Box<dyn Read> = match &bar {
+        Some(bar) => Box::new(bar.wrap_read(input)),
+        None => input,
+    };
+    let result = if args.decode {
+        BaseHanDecoder::new()
+            .with_chunk_size(args.chunk_size)
+            .decode_file(input, output)
     } else {
-        let mut buf = vec![0u8; args.chunk_size];
-        let mut encoder = BaseHanEncoder::new();
-        loop {
-            buf.fill(0);
-            let n = io::stdin().read(&mut buf).unwrap();
-            if n == 0 {
-                let out = [encoder.finish()];
-                io::stdout().write_all(String::from_iter(out).as_bytes()).unwrap();
-                break;
-            }
-            let out = encoder.update(&buf[..n]).unwrap();
-            io::stdout().write_all(String::from_iter(out).as_bytes()).unwrap();
-            io::stdout().flush().unwrap();
-        }
+        BaseHanEncoder::new()
+            .with_chunk_size(args.chunk_size)
+            .encode_file(input, output)
+    };
+    if let Some(bar) = bar {
+        bar.finish();
+    }
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        exit(1);
     }
-    io::stdout().flush().unwrap();
 }
 
 #[allow(dead_code)]
@@ -116,7 +185,7 @@ fn v0(args: Args) {
     let mut buffer = Vec::new();
     io::stdin()
         .read_to_end(&mut buffer)
-        .map_err(|e| BaseHanError::InternalError(format!("Failed to read from stdin: {:?}", e)))
+        .map_err(BaseHanError::from)
         .unwrap_or_else(|e| error_handler(e));
     if args.decode {
         // check is string
@@ -125,30 +194,30 @@ fn v0(args: Args) {
                 BaseHanError::InternalError(format!("Failed to convert to string: {:?}", e))
             })
             .unwrap_or_else(|e| error_handler(e));
-        let mut result = basehan::decode(&buffer).unwrap_or_else(|err| error_handler(err));
+        let result = basehan::decode(&buffer).unwrap_or_else(|err| error_handler(err));
         // let result = String::from_utf8(result).expect("Internal bugs occurred when decoding.").to_string();
         // result.push('\n' as u8);
         io::stdout()
             .write_all(&result)
-            .map_err(|e| BaseHanError::InternalError(format!("Failed to write to stdout: {:?}", e)))
+            .map_err(BaseHanError::from)
             .unwrap_or_else(|e| error_handler(e));
     } else {
-        let mut result = basehan::encode(buffer).unwrap_or_else(|err| error_handler(err));
+        let result = basehan::encode(buffer).unwrap_or_else(|err| error_handler(err));
         // result.push('\n');
         io::stdout()
             .write_all(result.as_bytes())
-            .map_err(|e| BaseHanError::InternalError(format!("Failed to write to stdout: {:?}", e)))
+            .map_err(BaseHanError::from)
             .unwrap_or_else(|e| error_handler(e));
     }
     io::stdout()
         .flush()
-        .map_err(|e| BaseHanError::InternalError(format!("Failed to write to stdout: {:?}", e)))
+        .map_err(BaseHanError::from)
         .unwrap_or_else(|e| error_handler(e));
 }
 
 fn main() {
     let args = Args::parse();
-    return v1(args);
+    v1(args)
 }
 
 fn error_handler(err: BaseHanError) -> ! {
@@ -159,6 +228,9 @@ fn error_handler(err: BaseHanError) -> ! {
         BaseHanError::InvalidCode(code, pos) => {
             eprintln!("Invalid input: code {:#x} at pos {}", code, pos);
         }
+        BaseHanError::IoError(e) => {
+            eprintln!("IO error: {}", e);
+        }
     }
     exit(1);
 }
@@ -0,0 +1,24 @@
+#![cfg(not(feature = "std"))]
+
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder, BaseHanError};
+
+#[test]
+fn streams_without_std() {
+    let mut encoder = BaseHanEncoder::new();
+    let mut encoded = encoder.update(b"alloc ").unwrap();
+    encoded.extend(encoder.update(b"only").unwrap());
+    encoded.push(encoder.finish().unwrap());
+
+    let mut decoder = BaseHanDecoder::new();
+    let mut decoded = Vec::new();
+    for chunk in encoded.chunks(2) {
+        decoded.extend(decoder.update(chunk).unwrap());
+    }
+    assert_eq!(decoder.finish(), Ok(None));
+    assert_eq!(decoded, b"alloc only");
+
+    // Errors without std still display, only `IoError` is gone
+    let err = BaseHanDecoder::decode_str_to_bytes("\u{4e00}").unwrap_err();
+    assert_eq!(err, BaseHanError::TruncatedStream);
+    assert!(!format!("{}", err).is_empty());
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "tokio")]
+
+use basehan::async_io::{decode_async, encode_async, AsyncBaseHanDecoder, AsyncBaseHanEncoder};
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder, BaseHanError};
+use std::io::ErrorKind;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn round_trip() {
+    let data: Vec<u8> = (0..=255).cycle().take(20_000).collect();
+    let mut encoded = Vec::new();
+    assert_eq!(encode_async(&mut data.as_slice(), &mut encoded).await.unwrap(), data.len() as u64);
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&data).unwrap().into_bytes());
+
+    let mut decoded = Vec::new();
+    assert_eq!(decode_async(encoded.as_slice(), &mut decoded).await.unwrap(), data.len() as u64);
+    assert_eq!(decoded, data);
+}
+
+#[tokio::test]
+async fn truncated_stream() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"async truncated").unwrap();
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+
+    let err = AsyncBaseHanDecoder::new(truncated.as_bytes()).read_to_end(&mut Vec::new()).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    match decode_async(truncated.as_bytes(), &mut Vec::new()).await {
+        Err(BaseHanError::IoError(e)) => assert_eq!(e.kind(), ErrorKind::UnexpectedEof),
+        other => panic!("expected UnexpectedEof, got {:?}", other),
+    }
+    // Empty input is an empty stream
+    assert_eq!(decode_async(&b""[..], &mut Vec::new()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn adapters_with_configured_codecs() {
+    let data = b"written in several chunks, read back in small ones";
+    let encoder = BaseHanEncoder::with_offset(0x3400).unwrap();
+    let mut writer = AsyncBaseHanEncoder::with_encoder(Vec::new(), encoder);
+    for chunk in data.chunks(6) {
+        writer.write_all(chunk).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+    writer.shutdown().await.unwrap();
+    let encoded = String::from_utf8(writer.into_inner()).unwrap();
+    let mut expected = BaseHanEncoder::with_offset(0x3400).unwrap();
+    let mut chars = expected.update(data).unwrap();
+    chars.push(expected.finish().unwrap());
+    assert_eq!(encoded, String::from_iter(chars));
+
+    let decoder = BaseHanDecoder::with_offset(0x3400).unwrap();
+    let mut reader = AsyncBaseHanDecoder::with_decoder(encoded.as_bytes(), decoder);
+    let mut decoded = Vec::new();
+    let mut buf = [0u8; 3];
+    loop {
+        let n = reader.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(decoded, data);
+}
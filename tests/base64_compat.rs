@@ -0,0 +1,54 @@
+use basehan::v1::BaseHanError;
+use basehan::{decode_base64_compat, encode_base64_compat, BASE64_OFFSET, BASE64_PAD};
+
+const BASE64_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Map Base64 text to the compat encoding char by char
+fn from_base64(b64: &str) -> String {
+    b64.chars()
+        .map(|c| match BASE64_ALPHABET.find(c) {
+            Some(i) => char::from_u32(BASE64_OFFSET + i as u32).unwrap(),
+            None => BASE64_PAD,
+        })
+        .collect()
+}
+
+#[test]
+fn mirrors_base64() {
+    // RFC 4648 test vectors
+    for (raw, b64) in [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ] {
+        assert_eq!(encode_base64_compat(raw), from_base64(b64));
+        assert_eq!(decode_base64_compat(&from_base64(b64)).unwrap(), raw.as_bytes());
+    }
+}
+
+#[test]
+fn roundtrip_all_lengths() {
+    let raw: Vec<u8> = (0..=255).collect();
+    for len in 0..raw.len() {
+        let encoded = encode_base64_compat(&raw[..len]);
+        assert_eq!(encoded.chars().count(), len.div_ceil(3) * 4);
+        assert_eq!(decode_base64_compat(&encoded).unwrap(), &raw[..len]);
+    }
+}
+
+#[test]
+fn rejects_malformed_input() {
+    let pad = BASE64_PAD as u32;
+    assert_eq!(decode_base64_compat(&from_base64("Zm9")), Err(BaseHanError::TruncatedStream));
+    assert_eq!(decode_base64_compat(&from_base64("Z===")), Err(BaseHanError::InvalidCode(pad, 1)));
+    assert_eq!(decode_base64_compat(&from_base64("Zg=gZm9v")), Err(BaseHanError::InvalidCode(BASE64_OFFSET + 32, 3)));
+    assert_eq!(decode_base64_compat(&from_base64("Zg==Zm9v")), Err(BaseHanError::InvalidCode(BASE64_OFFSET + 25, 4)));
+    assert_eq!(
+        decode_base64_compat("Zm9v"),
+        Err(BaseHanError::CodeOutOfRange { code: 'Z' as u32, pos: 0, valid_min: BASE64_OFFSET, valid_max: pad })
+    );
+}
@@ -0,0 +1,100 @@
+use basehan::bits::{BitCache13, BitCache8};
+use basehan::bits::BitCache8Out::{Double, Single};
+
+#[test]
+fn bit_cache8_fill_every_state_and_input() {
+    for nbits in 0..8 {
+        for initial in 0..1u32 << nbits {
+            for bits in 0..1u32 << 13 {
+                let mut cache = BitCache8::new(initial, nbits);
+                let out = cache.fill(bits);
+
+                // Reference: the concatenated bit string, split into whole bytes & the rest
+                let total = nbits + 13;
+                let joined = (initial << 13) | bits;
+                let remain = total % 8;
+                let expected: Vec<u8> = (0..total / 8)
+                    .rev()
+                    .map(|i| (joined >> (remain + 8 * i)) as u8)
+                    .collect();
+                let bytes = match out {
+                    Single(byte) => vec![byte],
+                    Double(bytes) => bytes.to_vec(),
+                };
+                assert_eq!(
+                    bytes, expected,
+                    "nbits {} initial {:#x} bits {:#x}",
+                    nbits, initial, bits
+                );
+                assert_eq!(
+                    cache,
+                    BitCache8::new(joined, remain),
+                    "nbits {} initial {:#x} bits {:#x}",
+                    nbits,
+                    initial,
+                    bits
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn bit_cache13_fill_every_state_and_input() {
+    for nbits in 0..13 {
+        for initial in 0..1u32 << nbits {
+            for byte in 0..=255u8 {
+                let mut cache = BitCache13::new(initial, nbits);
+                let out = cache.fill(byte);
+
+                let total = nbits + 8;
+                let joined = (initial << 8) | byte as u32;
+                let expected = (total >= 13).then(|| joined >> (total - 13));
+                assert_eq!(out, expected, "nbits {} initial {:#x} byte {:#x}", nbits, initial, byte);
+                assert_eq!(cache.nbits(), total % 13);
+                assert_eq!(cache.dump(), joined & ((1 << (total % 13)) - 1));
+            }
+        }
+    }
+}
+
+#[test]
+fn bit_cache13_fill_completes_group_from_5_bits() {
+    // 5 + 8 bits make exactly one group, output whole with nothing left over
+    let mut cache = BitCache13::new(0xffff_ffff, 5);
+    assert_eq!(cache.fill(0xff), Some(0x1fff));
+    assert_eq!(cache.nbits(), 0);
+    assert_eq!(cache.dump(), 0);
+
+    let mut cache = BitCache13::new(0b10110, 5);
+    assert_eq!(cache.fill(0x01), Some(0b1_0110_0000_0001));
+}
+
+#[test]
+fn caches_pack_and_unpack_a_stream() {
+    // Bytes to 13-bit groups and back, through the public API only
+    let data: Vec<u8> = (0..=40).map(|i| (i * 57) as u8).collect();
+    let mut packer = BitCache13::default();
+    let groups: Vec<u32> = data.iter().filter_map(|&b| packer.fill(b)).collect();
+    assert_eq!(groups.len(), data.len() * 8 / 13);
+    let nbits = packer.nbits();
+    assert_eq!(nbits, data.len() * 8 % 13);
+    let tail = packer.dump();
+    assert!(tail < 1 << nbits);
+
+    let mut unpacker = BitCache8::default();
+    let mut out = Vec::new();
+    let mut push = |bytes| match bytes {
+        Single(byte) => out.push(byte),
+        Double(pair) => out.extend_from_slice(&pair),
+    };
+    for group in groups {
+        push(unpacker.fill(group));
+    }
+    if let Some(bytes) = unpacker.fill_tail(tail, nbits) {
+        push(bytes);
+    }
+    assert_eq!(unpacker.nbits(), 0);
+    assert_eq!(unpacker.dump(), None);
+    assert_eq!(out, data);
+}
@@ -0,0 +1,55 @@
+use basehan::v1::BaseHanEncoder;
+use basehan::{decoded_byte_count_upper_bound, encoded_char_count};
+
+fn actual_char_count(len: usize) -> usize {
+    let data = vec![0xa5u8; len];
+    BaseHanEncoder::encode_bytes_to_string(&data)
+        .unwrap()
+        .chars()
+        .count()
+}
+
+#[test]
+fn encoded_char_count_matches_encoder() {
+    for len in 0..=3 * 13 + 1 {
+        assert_eq!(encoded_char_count(len), actual_char_count(len), "len {}", len);
+    }
+}
+
+#[test]
+fn encoded_char_count_boundaries() {
+    assert_eq!(encoded_char_count(0), 0);
+    assert_eq!(encoded_char_count(1), 1);
+    assert_eq!(encoded_char_count(2), 2);
+    assert_eq!(encoded_char_count(12), 8);
+    assert_eq!(encoded_char_count(13), 9);
+    assert_eq!(encoded_char_count(14), 9);
+    assert_eq!(encoded_char_count(26), 17);
+    // No intermediate overflow
+    assert_eq!(encoded_char_count(usize::MAX), usize::MAX / 13 * 8 + usize::MAX % 13 * 8 / 13 + 1);
+}
+
+#[test]
+fn decoded_byte_count_upper_bound_is_tight() {
+    assert_eq!(decoded_byte_count_upper_bound(0), 0);
+    assert_eq!(decoded_byte_count_upper_bound(1), 1);
+    assert_eq!(decoded_byte_count_upper_bound(9), 14);
+    for len in 1..=4 * 13 {
+        let nchars = encoded_char_count(len);
+        assert!(decoded_byte_count_upper_bound(nchars) >= len, "len {}", len);
+        // The bound is reached by the longest input of that many chars
+        let max = decoded_byte_count_upper_bound(nchars);
+        assert_eq!(encoded_char_count(max), nchars, "len {}", len);
+        assert!(encoded_char_count(max + 1) > nchars, "len {}", len);
+    }
+    assert_eq!(decoded_byte_count_upper_bound(usize::MAX), usize::MAX);
+}
+
+#[test]
+fn estimate_capacity_matches_encoder() {
+    const CAPACITY: usize = BaseHanEncoder::estimate_capacity(13);
+    assert_eq!(CAPACITY, 9);
+    for len in 0..=100 {
+        assert_eq!(BaseHanEncoder::estimate_capacity(len), actual_char_count(len), "len {}", len);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "cli")]
+
+use basehan::v1::BaseHanEncoder;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn basehan(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_basehan"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn short_i_is_interactive() {
+    let output = basehan(&["-i"], b"abc\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("Interactive mode."), "{}", stdout);
+    assert!(stdout.contains(&BaseHanEncoder::encode_bytes_to_string(b"abc").unwrap()));
+}
+
+#[test]
+fn interactive_decode_rejects_trailing_input() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"hi").unwrap();
+    let output = basehan(&["-i", "-d"], format!("{}\n{}\u{4e00}\u{4e00}\n", encoded, encoded).as_bytes());
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[1], "hi", "{}", stdout);
+    assert!(lines[2].starts_with("Error: "), "{}", stdout);
+}
+
+#[test]
+fn input_reads_from_a_file() {
+    let path = std::env::temp_dir().join(format!("basehan-cli-input-{}", std::process::id()));
+    std::fs::write(&path, b"from a file").unwrap();
+    let output = basehan(&["--input", path.to_str().unwrap()], b"");
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        BaseHanEncoder::encode_bytes_to_string(b"from a file").unwrap()
+    );
+}
+
+#[test]
+fn chunk_size_is_validated() {
+    for chunk_size in ["0", "12", "-1", "many"] {
+        let output = basehan(&[&format!("--chunk-size={}", chunk_size)], b"");
+        assert_eq!(output.status.code(), Some(2), "chunk size {}", chunk_size);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("--chunk-size"), "{}", stderr);
+    }
+    assert!(String::from_utf8(basehan(&["-c", "12"], b"").stderr).unwrap().contains("at least 13 bytes"));
+
+    // The smallest chunk size still encodes and decodes all of the input
+    let data: Vec<u8> = (0..=255).collect();
+    let output = basehan(&["-c", "13"], &data);
+    assert!(output.status.success());
+    let encoded = output.stdout;
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&data).unwrap().into_bytes());
+    let output = basehan(&["-d", "-c", "13"], &encoded);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, data);
+}
@@ -0,0 +1,512 @@
+use basehan::encoded_char_count;
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder, BaseHanError, DecoderState};
+use std::io::Cursor;
+
+#[test]
+fn lenient_decoder_skips_punctuation() {
+    let data = b"Hello, Base-Han!";
+    let encoded = BaseHanEncoder::encode_bytes_to_string(data).unwrap();
+    let noisy: String = encoded
+        .chars()
+        .flat_map(|c| [c, '，', '「'])
+        .chain("。".chars())
+        .collect();
+
+    let err = BaseHanDecoder::new().update_str(&noisy).unwrap_err();
+    assert_eq!(
+        err,
+        BaseHanError::CodeOutOfRange { code: '，' as u32, pos: 1, valid_min: 0x4e00, valid_max: 0x8dff }
+    );
+
+    let mut decoder = BaseHanDecoder::new().lenient();
+    assert_eq!(decoder.update_str(&noisy).unwrap(), data);
+    assert!(decoder.is_finished());
+}
+
+#[test]
+fn lenient_decoder_skips_stray_percent_signs() {
+    let data = b"50% off";
+    let encoded = BaseHanEncoder::encode_bytes_to_string(data).unwrap();
+    let noisy = format!("50% {}", encoded);
+    assert_eq!(BaseHanDecoder::new().update_str(&noisy[2..]), Err(BaseHanError::InvalidCode(' ' as u32, 1)));
+    assert_eq!(BaseHanDecoder::new().lenient().update_str(&noisy).unwrap(), data);
+
+    // A '%' cut short by a data char or by another '%' loses neither
+    let chars: Vec<char> = encoded.chars().collect();
+    let noisy = format!("%4{}%%e{}", chars[0], String::from_iter(&chars[1..]));
+    let mut decoder = BaseHanDecoder::new().lenient();
+    assert_eq!(decoder.update_str(&noisy).unwrap(), data);
+    assert!(decoder.is_finished());
+
+    // Percent-encoded chars still decode
+    let mut encoder = BaseHanEncoder::new().url_safe();
+    let mut encoded = String::from_iter(encoder.update(data).unwrap());
+    encoded.extend(encoder.finish_chars().unwrap());
+    assert_eq!(BaseHanDecoder::new().lenient().update_str(&format!("% {}", encoded)).unwrap(), data);
+}
+
+#[test]
+fn finish_reports_truncated_stream() {
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"truncated")
+        .unwrap()
+        .chars()
+        .collect();
+
+    let mut decoder = BaseHanDecoder::new();
+    decoder.update(&encoded[..encoded.len() - 1]).unwrap();
+    assert_eq!(decoder.finish(), Err(BaseHanError::TruncatedStream));
+
+    let mut decoder = BaseHanDecoder::new();
+    decoder.update(&encoded).unwrap();
+    assert_eq!(decoder.finish(), Ok(None));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_buf_read_decodes_each_line() {
+    let messages: [&[u8]; 3] = [b"first", b"", b"third message"];
+    let mut input = String::new();
+    for message in messages {
+        input += &BaseHanEncoder::encode_bytes_to_string(message).unwrap();
+        input += "\r\n";
+    }
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"cut").unwrap().chars().collect();
+    input.extend(&encoded[..encoded.len() - 1]);
+
+    let decoded: Vec<_> = BaseHanDecoder::from_buf_read(Cursor::new(input)).collect();
+    assert_eq!(decoded.len(), 4);
+    for (message, decoded) in messages.iter().zip(&decoded) {
+        assert_eq!(decoded.as_deref(), Ok(*message));
+    }
+    assert_eq!(decoded[3], Err(BaseHanError::TruncatedStream));
+
+    // Two messages on one line are an error rather than the first one
+    let line = BaseHanEncoder::encode_bytes_to_string(b"one").unwrap().repeat(2);
+    let decoded: Vec<_> = BaseHanDecoder::from_buf_read(Cursor::new(line + "\n")).collect();
+    assert_eq!(decoded, [Err(BaseHanError::EndOfFile)]);
+}
+
+#[test]
+fn state_tracks_the_stream() {
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"state machine")
+        .unwrap()
+        .chars()
+        .collect();
+    let (body, terminator) = encoded.split_at(encoded.len() - 1);
+
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.state(), DecoderState::Streaming);
+    decoder.update(body).unwrap();
+    assert_eq!(decoder.state(), DecoderState::Streaming);
+    decoder.update(terminator).unwrap();
+    assert_eq!(decoder.state(), DecoderState::Finished);
+    assert_eq!(decoder.update(body), Err(BaseHanError::EndOfFile));
+    assert_eq!(decoder.state(), DecoderState::Error(BaseHanError::EndOfFile));
+
+    let mut decoder = BaseHanDecoder::new();
+    let err = decoder.update_str("abc").unwrap_err();
+    decoder.update(body).unwrap();
+    assert_eq!(decoder.state(), DecoderState::Error(err)); // Errors stick
+}
+
+#[test]
+fn validate_without_decoding() {
+    let decoder = BaseHanDecoder::new();
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"validated input").unwrap();
+    assert_eq!(decoder.validate(&encoded), Ok(()));
+    assert_eq!(decoder.validate(&format!(" {}\n\u{3000}", encoded)), Ok(()));
+    assert_eq!(decoder.validate(""), Ok(()));
+
+    let invalid = format!("{}A{}", &encoded[..6], &encoded[6..]);
+    let out_of_range = |code, pos| BaseHanError::CodeOutOfRange { code, pos, valid_min: 0x4e00, valid_max: 0x8dff };
+    assert_eq!(decoder.validate(&invalid), Err(out_of_range('A' as u32, 2)));
+    assert_eq!(decoder.validate("\u{6e00}"), Err(BaseHanError::InvalidCode(0x6e00, 0)));
+    assert_eq!(decoder.validate("\u{8e00}"), Err(out_of_range(0x8e00, 0)));
+    assert_eq!(decoder.validate("\u{4dff}"), Err(out_of_range(0x4dff, 0)));
+
+    // Lenient mode passes what it would skip, but not a broken terminator
+    let lenient = BaseHanDecoder::new().lenient();
+    assert_eq!(lenient.validate(&invalid), Ok(()));
+    assert_eq!(lenient.validate("\u{6e00}"), Err(BaseHanError::InvalidCode(0x6e00, 0)));
+}
+
+#[test]
+fn validate_with_custom_offset() {
+    let mut encoder = BaseHanEncoder::with_offset(0x3400).unwrap();
+    let mut encoded = encoder.update(b"offset").unwrap();
+    encoded.push(encoder.finish().unwrap());
+    let encoded: String = encoded.into_iter().collect();
+    let decoder = BaseHanDecoder::with_offset(0x3400).unwrap();
+    assert_eq!(decoder.validate(&encoded), Ok(()));
+    // The default data chars are out of this decoder's range
+    let out_of_range = BaseHanError::CodeOutOfRange { code: 0x6000, pos: 0, valid_min: 0x3400, valid_max: 0x8dff };
+    assert_eq!(decoder.validate("\u{6000}"), Err(out_of_range));
+    assert!(BaseHanDecoder::new().validate(&encoded).is_err());
+}
+
+#[test]
+fn update_from_str_iter_across_lines() {
+    use std::io::BufRead;
+
+    let data: Vec<u8> = (0..100).collect();
+    let mut encoder = BaseHanEncoder::new().with_line_width(16);
+    let mut encoded = String::from_iter(encoder.update(&data).unwrap());
+    encoded.push(encoder.finish().unwrap());
+    let mut decoder = BaseHanDecoder::new();
+    let mut decoded = Vec::new();
+    for line in Cursor::new(encoded).lines() {
+        decoded.extend(decoder.update_from_str_iter(line.unwrap().chars()).unwrap());
+    }
+    assert!(decoder.is_finished());
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn pending_bytes_between_calls() {
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.pending_bytes(), 0);
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"pending").unwrap();
+    let encoded: Vec<char> = encoded.chars().collect();
+    // 2 chars are 26 bits: 3 whole bytes pending, the 2 bits left over are not counted
+    assert_eq!(decoder.feed(&encoded[..2]).unwrap(), 3);
+    assert_eq!(decoder.pending_bytes(), 3);
+    assert_eq!(decoder.take_bytes(1).unwrap(), b"p");
+    assert_eq!(decoder.pending_bytes(), 2);
+    // Other decoding methods hand out everything pending
+    assert_eq!(decoder.update(&encoded[2..]).unwrap(), b"ending");
+    assert_eq!(decoder.pending_bytes(), 0);
+}
+
+#[test]
+fn skip_drops_decoded_bytes() {
+    let data: Vec<u8> = (0..50).collect();
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(&data).unwrap().chars().collect();
+
+    let mut decoder = BaseHanDecoder::new();
+    decoder.skip(20).unwrap();
+    let mut decoded = decoder.update(&encoded[..5]).unwrap(); // 8 bytes, all skipped
+    assert!(decoded.is_empty());
+    decoded.extend(decoder.update(&encoded[5..]).unwrap());
+    assert_eq!(decoded, &data[20..]);
+    assert_eq!(decoder.skip(1), Err(BaseHanError::EndOfFile));
+
+    let mut decoder = BaseHanDecoder::new();
+    decoder.skip(100).unwrap();
+    assert!(decoder.update(&encoded).unwrap().is_empty());
+
+    // Bytes pending from feed are dropped first, the rest from those decoded next
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.feed(&encoded[..5]).unwrap(), 8);
+    decoder.skip(3).unwrap();
+    assert_eq!(decoder.pending_bytes(), 5);
+    assert_eq!(decoder.take_bytes(2).unwrap(), &data[3..5]);
+    decoder.skip(10).unwrap();
+    assert_eq!(decoder.pending_bytes(), 0);
+    assert_eq!(decoder.update(&encoded[5..]).unwrap(), &data[15..]);
+
+    // Once finished, only pending bytes can be dropped
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.feed(&encoded).unwrap(), 50);
+    decoder.skip(45).unwrap();
+    assert_eq!(decoder.skip(6), Err(BaseHanError::EndOfFile));
+    assert_eq!(decoder.take_bytes(5).unwrap(), &data[45..]);
+}
+
+#[test]
+fn update_takes_any_char_iterator() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"iterators").unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+
+    assert_eq!(BaseHanDecoder::new().update(encoded.chars()).unwrap(), b"iterators");
+    assert_eq!(BaseHanDecoder::new().update(&chars).unwrap(), b"iterators");
+    assert_eq!(BaseHanDecoder::new().update(&chars[..]).unwrap(), b"iterators");
+    assert_eq!(BaseHanDecoder::new().update(chars.iter().filter(|&&c| c != ' ')).unwrap(), b"iterators");
+    assert_eq!(BaseHanDecoder::new().update(chars).unwrap(), b"iterators");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn decode_into_writer_across_chunks() {
+    let data: Vec<u8> = (0..200).collect();
+    let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+    let mut decoder = BaseHanDecoder::new();
+    let mut out = Vec::new();
+    let mut total = 0;
+    for chunk in chars.chunks(7) {
+        total += decoder.decode_into_writer(chunk.iter().copied(), &mut out).unwrap();
+    }
+    assert_eq!(total, data.len());
+    assert_eq!(out, data);
+    assert!(decoder.is_finished());
+}
+
+#[test]
+fn terminator_must_match_the_remaining_bits() {
+    let mut encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"0123456789abc")
+        .unwrap()
+        .chars()
+        .collect();
+    assert_eq!(encoded.len(), 9); // 8 full chars and an empty terminator
+    encoded.remove(3); // A data char lost mid-stream, leaving 3 bits out of the last byte
+    assert_eq!(
+        BaseHanDecoder::new().update(&encoded),
+        Err(BaseHanError::InvalidTerminator(0x6e01, 7))
+    );
+
+    let mut encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"abc").unwrap().chars().collect();
+    *encoded.last_mut().unwrap() = char::from_u32(0x6e00 + (1 << 4 | 0b0101)).unwrap();
+    assert_eq!(
+        BaseHanDecoder::new().update(&encoded),
+        Err(BaseHanError::InvalidTerminator(0x6e15, 1))
+    );
+}
+
+#[test]
+fn chars_consumed_stops_at_the_terminator() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"embedded").unwrap();
+    let json = format!("{}\",\"next\":1}}", encoded);
+
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.update_str(&json).unwrap(), b"embedded");
+    let consumed = decoder.chars_consumed();
+    assert_eq!(consumed, encoded.chars().count());
+    assert_eq!(json.chars().skip(consumed).collect::<String>(), "\",\"next\":1}");
+
+    decoder.reset();
+    assert_eq!(decoder.chars_consumed(), 0);
+    assert_eq!(decoder.state(), DecoderState::Streaming);
+    let chars: Vec<char> = encoded.chars().collect();
+    decoder.update(&chars[..3]).unwrap();
+    assert_eq!(decoder.chars_consumed(), 3);
+    decoder.update(&chars[3..]).unwrap();
+    assert_eq!(decoder.chars_consumed(), chars.len());
+}
+
+#[test]
+fn take_bytes_frames_messages() {
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"headerbody-of-message")
+        .unwrap()
+        .chars()
+        .collect();
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.feed(&encoded[..2]).unwrap(), 3);
+    assert_eq!(decoder.take_bytes(6), None);
+    assert_eq!(decoder.feed(&encoded[2..5]).unwrap(), 8);
+    assert_eq!(decoder.take_bytes(6).unwrap(), b"header");
+    assert_eq!(decoder.pending_bytes(), 2);
+    decoder.feed(&encoded[5..]).unwrap();
+    assert_eq!(decoder.take_bytes(4).unwrap(), b"body");
+    assert_eq!(decoder.take_bytes(0).unwrap(), b"");
+    assert_eq!(decoder.update(Vec::<char>::new()).unwrap(), b"-of-message");
+    assert!(decoder.is_finished());
+}
+
+#[test]
+fn decode_into_slice_reports_the_size_needed() {
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"fixed budget").unwrap().chars().collect();
+    let mut out = [0u8; 16];
+    let n = BaseHanDecoder::new().decode_into_slice(&encoded, &mut out).unwrap();
+    assert_eq!(&out[..n], b"fixed budget");
+
+    let mut decoder = BaseHanDecoder::new();
+    let mut small = [0u8; 4];
+    assert_eq!(decoder.decode_into_slice(&encoded, &mut small), Err(BaseHanError::BufferTooSmall(12)));
+    assert_eq!(decoder.decode_into_slice(&[], &mut out), Ok(12));
+    assert_eq!(&out[..12], b"fixed budget");
+}
+
+#[test]
+fn multi_message_splits_concatenated_streams() {
+    let payloads: [&[u8]; 4] = [b"first", b"\x00", b"third message", b"last"];
+    let concatenated: Vec<char> = payloads
+        .iter()
+        .flat_map(|p| BaseHanEncoder::encode_bytes_to_prefixed_string(p).unwrap().chars().collect::<Vec<_>>())
+        .collect();
+
+    for chunk_size in [1, 3, concatenated.len()] {
+        let mut decoder = BaseHanDecoder::new().with_header(true).with_multi_message(true);
+        let mut messages = Vec::new();
+        for chunk in concatenated.chunks(chunk_size) {
+            messages.extend(decoder.update_messages(chunk).unwrap());
+        }
+        assert_eq!(messages, payloads);
+        assert_eq!(decoder.pending_bytes(), 0);
+    }
+
+    let mut decoder = BaseHanDecoder::new().with_multi_message(true);
+    let two = format!("{}{}", BaseHanEncoder::encode_bytes_to_string(b"one").unwrap(), "\u{4e00}");
+    assert_eq!(decoder.update_messages(two.chars()).unwrap(), [b"one"]);
+    assert_eq!(decoder.state(), DecoderState::Streaming); // The second message is in progress
+    // A single-message decoder stops at the first terminator
+    assert_eq!(BaseHanDecoder::new().update_str(&two).unwrap(), b"one");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sink_receives_every_chunk() {
+    let raw: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let encoded = BaseHanEncoder::encode_bytes_to_string(&raw).unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+
+    let mut decoder = BaseHanDecoder::new().with_sink(Vec::new());
+    let mut written = 0;
+    for chunk in chars.chunks(7) {
+        written += decoder.update(chunk).unwrap();
+    }
+    assert!(decoder.is_finished());
+    assert_eq!(written, raw.len());
+    assert_eq!(decoder.finish().unwrap(), raw);
+
+    let decoder = BaseHanDecoder::new().with_sink(Vec::new());
+    assert_eq!(decoder.finish().unwrap(), b"");
+    let mut decoder = BaseHanDecoder::new().with_sink(Vec::new());
+    decoder.update_str(&encoded[..30]).unwrap();
+    assert_eq!(decoder.finish().unwrap_err(), BaseHanError::TruncatedStream);
+}
+
+#[test]
+fn chars_remaining_estimate_tracks_progress() {
+    for len in 0..40 {
+        let raw: Vec<u8> = (0..len as u8).collect();
+        let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(&raw).unwrap().chars().collect();
+        // Whichever of len and len + 1 bytes encodes to as many chars is the estimate
+        let longest = (len..=len + 1).filter(|&n| encoded_char_count(n) == encoded.len()).max().unwrap();
+
+        let mut decoder = BaseHanDecoder::new();
+        let mut decoded = 0;
+        for (i, c) in encoded.iter().enumerate() {
+            let estimate = decoder.chars_remaining_estimate(encoded.len()).unwrap();
+            assert_eq!(estimate, longest - decoded, "len {} at char {}", len, i);
+            decoded += decoder.update([*c]).unwrap().len();
+        }
+        assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(0));
+    }
+
+    let mut decoder = BaseHanDecoder::new();
+    decoder.update_str("\u{4e00}\u{4e00}").unwrap();
+    assert_eq!(decoder.chars_remaining_estimate(2), None);
+}
+
+#[test]
+fn chars_remaining_estimate_skips_the_header() {
+    let raw = b"header first";
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_prefixed_string(raw).unwrap().chars().collect();
+    let mut decoder = BaseHanDecoder::new().with_header(true);
+    assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(raw.len()));
+    decoder.update(&encoded[..1]).unwrap();
+    assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(raw.len()));
+    decoder.update(&encoded[1..]).unwrap();
+    assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(0));
+}
+
+#[test]
+fn decode_lossy_drops_stray_chars() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"lossy decoding").unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+    // An OCR-like stray char, an invalid terminator mid-stream, and trailing garbage
+    let mut noisy = String::from_iter(&chars[..3]);
+    noisy += "x\u{6e00}";
+    noisy.extend(&chars[3..]);
+    noisy += "\u{4e00}";
+
+    assert!(BaseHanDecoder::decode_str_to_bytes(&noisy).is_err());
+    assert_eq!(basehan::decode_lossy(&noisy), b"lossy decoding");
+    assert_eq!(basehan::decode_lossy(""), b"");
+    // A truncated stream keeps the whole bytes
+    assert_eq!(basehan::decode_lossy(&String::from_iter(&chars[..4])), b"lossy ");
+}
+
+#[test]
+fn one_shot_decoding_requires_a_whole_stream() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"whole stream").unwrap();
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&truncated), Err(BaseHanError::TruncatedStream));
+
+    let trailing = encoded.clone() + "\u{4e00}";
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&trailing), Err(BaseHanError::EndOfFile));
+    let trailing = encoded.clone() + "\n" + &encoded;
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&trailing), Err(BaseHanError::EndOfFile));
+    assert_eq!(BaseHanDecoder::decode_to_u16_be(&trailing), Err(BaseHanError::EndOfFile));
+    assert_eq!(BaseHanDecoder::auto_detect(&truncated), Err(BaseHanError::TruncatedStream));
+
+    // Whitespace and padding may follow, and make an empty stream on their own
+    let padded = encoded.clone() + "\u{3000}\u{3000}\n";
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&padded).unwrap(), b"whole stream");
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(" \n").unwrap(), b"");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_reports_truncated_stream() {
+    use basehan::v1::BaseHanReader;
+    use std::io::{ErrorKind, Read};
+
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"read to the end").unwrap();
+    let mut out = Vec::new();
+    BaseHanReader::from(encoded.as_str()).read_to_end(&mut out).unwrap();
+    assert_eq!(out, b"read to the end");
+
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+    let err = BaseHanReader::from(truncated.as_str()).read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(BaseHanReader::from("").read_to_end(&mut out).unwrap(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_fills_small_buffers() {
+    use basehan::v1::BaseHanReader;
+    use std::io::{ErrorKind, Read};
+
+    let data: Vec<u8> = (0..=255).collect();
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(&data).unwrap().chars().collect();
+    for size in [1, 5, 64] {
+        let mut reader = BaseHanReader::from(&encoded[..]);
+        let mut buf = vec![0; size];
+        let mut out = Vec::new();
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            assert!(n <= size);
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(out, data, "buffer size {}", size);
+    }
+
+    let err = BaseHanReader::from("\u{4e00}a").read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn null_chars_are_corrupted_input() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"no truncation at null").unwrap();
+    let pos = 3;
+    let (head, tail) = encoded.split_at(encoded.char_indices().nth(pos).unwrap().0);
+    let with_null = format!("{}\0{}", head, tail);
+    assert_eq!(
+        BaseHanDecoder::new().update_str(&with_null),
+        Err(BaseHanError::CodeOutOfRange { code: 0, pos, valid_min: 0x4e00, valid_max: 0x8dff })
+    );
+    // Null chars are an error rather than zero bytes
+    assert!(BaseHanDecoder::new().update(['\0'; 8]).is_err());
+}
+
+#[test]
+fn update_str_matches_update() {
+    let data: Vec<u8> = (0..100u8).map(|i| i.wrapping_mul(3)).collect();
+    let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+    let split = encoded.char_indices().nth(17).unwrap().0;
+
+    let mut decoder = BaseHanDecoder::new();
+    let mut decoded = decoder.update_str(&encoded[..split]).unwrap();
+    assert_eq!(decoded, BaseHanDecoder::new().update(&chars[..17]).unwrap());
+    decoded.extend(decoder.update_str(&encoded[split..]).unwrap());
+    assert_eq!(decoded, data);
+    // Padding may follow the terminator in a later call, but nothing else
+    assert_eq!(decoder.update_str("\u{3000}\n").unwrap(), b"");
+    assert_eq!(decoder.update_str(&encoded), Err(BaseHanError::EndOfFile));
+}
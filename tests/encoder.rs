@@ -0,0 +1,511 @@
+use basehan::v1::{encode_debug, BaseHanDecoder, BaseHanEncoder, BaseHanError, BaseHanString, StringBaseHanEncoder, PAD_CHAR, VERSION_PREFIX};
+
+#[test]
+fn update_slice_matches_sub_slice() {
+    let data = b"0123456789abcdef";
+    let mut expected = BaseHanEncoder::new();
+    let mut encoder = BaseHanEncoder::new();
+    assert_eq!(
+        encoder.update_slice(data, 3, 10).unwrap(),
+        expected.update(&data[3..13]).unwrap()
+    );
+    assert_eq!(encoder.update_slice(data, 16, 0).unwrap(), vec![]);
+    assert_eq!(encoder.finish(), expected.finish());
+}
+
+#[test]
+fn update_slice_rejects_out_of_bounds() {
+    let mut encoder = BaseHanEncoder::new();
+    assert_eq!(encoder.update_slice(b"abc", 2, 2), Err(BaseHanError::InvalidRange));
+    assert_eq!(encoder.update_slice(b"abc", 4, 0), Err(BaseHanError::InvalidRange));
+    assert_eq!(encoder.update_slice(b"abc", 1, usize::MAX), Err(BaseHanError::InvalidRange));
+}
+
+#[test]
+fn encode_debug_escapes_code_points() {
+    // 0x61 = 0b01100001: 8 remaining bits behind a leading 1 in the terminator
+    assert_eq!(encode_debug(b"a").unwrap(), "\\u{6F61}");
+    assert_eq!(encode_debug(b"").unwrap(), "");
+    let debug = encode_debug(b"hello").unwrap();
+    let expected: String = BaseHanEncoder::encode_bytes_to_string(b"hello")
+        .unwrap()
+        .chars()
+        .map(|c| format!("\\u{{{:04X}}}", c as u32))
+        .collect();
+    assert_eq!(debug, expected);
+}
+
+#[test]
+fn string_encoder_matches_one_shot() {
+    let data: Vec<u8> = (0..100u8).collect();
+    let mut encoder = StringBaseHanEncoder::new();
+    let mut out = String::new();
+    for chunk in data.chunks(7) {
+        encoder.update(chunk);
+        out += &encoder.take_string();
+    }
+    out += &encoder.finish_into_string();
+    assert_eq!(out, BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+
+    assert_eq!(StringBaseHanEncoder::new().finish_into_string(), "");
+    let mut url_safe = StringBaseHanEncoder::from(BaseHanEncoder::new().url_safe());
+    url_safe.update(b"a");
+    assert_eq!(url_safe.finish_into_string(), "%E6%BD%A1");
+}
+
+#[test]
+fn finish_padded_aligns_to_a_multiple() {
+    for len in 0..30 {
+        let data = vec![0x3c; len + 1];
+        let mut encoder = BaseHanEncoder::new();
+        let mut encoded = encoder.update(&data).unwrap();
+        let unpadded = encoded.len() + 1;
+        encoded.extend(encoder.finish_padded(4).unwrap());
+        assert_eq!(encoded.len() % 4, 0, "len {}", len + 1);
+        assert!(encoded.len() - unpadded < 4);
+        assert!(encoded[unpadded..].iter().all(|&c| c == PAD_CHAR));
+        assert_eq!(BaseHanDecoder::decode_str_to_bytes(&String::from_iter(&encoded)).unwrap(), data);
+    }
+
+    let mut encoder = BaseHanEncoder::new();
+    encoder.update(b"no padding").unwrap();
+    assert_eq!(encoder.finish_padded(0).unwrap().len(), 1);
+}
+
+#[test]
+fn pad_to_fixed_length() {
+    let mut encoder = BaseHanEncoder::new();
+    let mut encoded = encoder.update(b"fixed size").unwrap();
+    encoded.extend(encoder.pad_to(16).unwrap());
+    assert_eq!(encoded.len(), 16);
+    assert_eq!(encoded.last(), Some(&PAD_CHAR));
+    assert_eq!(BaseHanDecoder::new().update(&encoded).unwrap(), b"fixed size");
+
+    let mut encoder = BaseHanEncoder::new();
+    let mut exact = encoder.update(b"fixed size").unwrap();
+    exact.extend(encoder.pad_to(7).unwrap());
+    assert_eq!(exact.len(), 7);
+
+    let mut encoder = BaseHanEncoder::new();
+    encoder.update(b"fixed size").unwrap();
+    assert_eq!(encoder.pad_to(6), Err(BaseHanError::ExceedsTarget(7)));
+}
+
+#[test]
+fn padding_counts_the_header() {
+    let mut encoder = BaseHanEncoder::new().with_header(true);
+    let mut encoded = encoder.update(b"fixed size").unwrap();
+    encoded.extend(encoder.pad_to(16).unwrap());
+    assert_eq!(encoded.len(), 16);
+    assert_eq!(encoded[0], VERSION_PREFIX);
+    assert_eq!(BaseHanDecoder::new().with_header(true).update(&encoded).unwrap(), b"fixed size");
+
+    let mut encoder = BaseHanEncoder::new().with_header(true);
+    encoder.update(b"fixed size").unwrap();
+    assert_eq!(encoder.pad_to(7), Err(BaseHanError::ExceedsTarget(8)));
+
+    let mut encoder = BaseHanEncoder::new().with_header(true);
+    let mut encoded = encoder.update(b"fixed size").unwrap();
+    encoded.extend(encoder.finish_padded(4).unwrap());
+    assert_eq!(encoded.len(), 8);
+}
+
+#[test]
+fn only_the_terminator_reaches_ending_offset() {
+    let data: Vec<u8> = (0..=255).collect();
+    let mut encoder = BaseHanEncoder::new();
+    let encoded = encoder.update(&data).unwrap();
+    assert!(encoded.iter().all(|&c| (c as u32) < basehan::ENDING_OFFSET));
+    let terminator = encoder.finish().unwrap();
+    assert!(terminator as u32 >= basehan::ENDING_OFFSET);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn write_to_drains_written_chars() {
+    use std::io::Write;
+
+    let mut encoder = BaseHanEncoder::new();
+    encoder.write_all(b"write to").unwrap();
+    encoder.flush().unwrap();
+    let mut out = Vec::new();
+    encoder.write_to(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), BaseHanEncoder::encode_bytes_to_string(b"write to").unwrap());
+    assert!(encoder.encoded_chars().is_empty());
+
+    encoder.write_all(b"kept").unwrap();
+    let mut full = [0u8; 2];
+    assert!(matches!(encoder.write_to(&mut full[..]), Err(BaseHanError::IoError(_))));
+    assert!(!encoder.encoded_chars().is_empty());
+}
+
+#[test]
+fn basehan_string_behaves_like_str() {
+    let mut encoder = BaseHanEncoder::new();
+    let mut chars = encoder.update(b"display").unwrap();
+    chars.push(encoder.finish().unwrap());
+    let encoded = BaseHanString::from(chars);
+    let expected = BaseHanEncoder::encode_bytes_to_string(b"display").unwrap();
+
+    assert_eq!(format!("{}", encoded), expected);
+    assert_eq!(encoded.chars().count(), 5);
+    assert_eq!(encoded.as_ref(), expected.as_str());
+    assert_eq!(String::from("=") + &encoded, format!("={}", expected));
+    assert_eq!(encoded.as_bytes(), expected.as_bytes());
+    assert_eq!(encoded.clone().into_bytes(), expected.clone().into_bytes());
+    assert_eq!(BaseHanString::from(expected), encoded);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn pending_chars_until_drained() {
+    use std::io::Write;
+
+    let mut encoder = BaseHanEncoder::new();
+    assert_eq!(encoder.pending_chars(), 0);
+    encoder.write_all(&[0u8; 13]).unwrap();
+    assert_eq!(encoder.pending_chars(), 8);
+    encoder.flush().unwrap();
+    assert_eq!(encoder.pending_chars(), 9);
+    assert_eq!(encoder.encoded_chars().len(), 9);
+    assert_eq!(encoder.pending_chars(), 0);
+}
+
+#[test]
+fn encoding_range_matches_with_offset() {
+    use basehan::v1::verify_encoding_range;
+
+    for offset in [0, 0x4e00, 0xa000, 0x10_dfff, 0x10_e000, 0xc000, 0xd800, 0x5000, 0x8e00, u32::MAX] {
+        assert_eq!(verify_encoding_range(offset), BaseHanEncoder::with_offset(offset).is_ok(), "{:#x}", offset);
+    }
+    assert!(verify_encoding_range(0x4e00));
+    assert!(verify_encoding_range(0x10_e000));
+    assert!(!verify_encoding_range(0x10_e001));
+    assert!(!verify_encoding_range(0xc000)); // Runs into the surrogates
+    assert!(!verify_encoding_range(0x5000)); // Runs into the terminators
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_reader_encodes_all() {
+    let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let expected = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    assert_eq!(BaseHanEncoder::from_reader(&data[..]).encode_all().unwrap(), expected);
+    assert_eq!(
+        BaseHanEncoder::from_reader(&data[..]).with_chunk_size(7).encode_all().unwrap(),
+        expected
+    );
+    assert_eq!(BaseHanEncoder::from_reader(std::io::empty()).encode_all().unwrap(), "");
+}
+
+#[test]
+fn encode_repeat_matches_update() {
+    for prefix in [&b""[..], b"x", b"0123456789ab"] {
+        for count in [0, 1, 12, 13, 26, 27, 39, 1000] {
+            let mut expected = BaseHanEncoder::new();
+            let mut expected_out = expected.update(prefix).unwrap();
+            expected_out.extend(expected.update(vec![0xa5; count]).unwrap());
+            expected_out.extend(expected.finish_chars().unwrap_or_default());
+
+            let mut encoder = BaseHanEncoder::new().with_header(true);
+            let mut out = encoder.update(prefix).unwrap();
+            out.extend(encoder.encode_repeat(0xa5, count).unwrap());
+            out.extend(encoder.finish_chars().unwrap_or_default());
+            let skip = usize::from(!out.is_empty()); // The header
+            assert_eq!(out[skip..], expected_out[..], "{:?} {}", prefix, count);
+        }
+    }
+}
+
+#[test]
+fn line_width_chains_with_other_settings() {
+    let mut encoder = BaseHanEncoder::with_offset(0x3400).unwrap().with_line_width(4).line_separator("\r\n");
+    let mut encoded = String::from_iter(encoder.update([0x5a; 20]).unwrap());
+    encoded.push(encoder.finish().unwrap());
+    let lines: Vec<&str> = encoded.split("\r\n").collect();
+    assert_eq!(lines.iter().map(|line| line.chars().count()).collect::<Vec<_>>(), [4, 4, 4, 1]);
+    assert!(lines[0].chars().all(|c| (0x3400..0x5400).contains(&(c as u32))));
+
+    let mut decoder = BaseHanDecoder::with_offset(0x3400).unwrap();
+    assert_eq!(decoder.update_str(&encoded).unwrap(), [0x5a; 20]);
+    assert!(decoder.is_finished());
+}
+
+#[test]
+fn basehan_string_counts_chars_and_bits() {
+    for len in [0, 1, 2, 12, 13, 14, 100] {
+        let data = vec![0x5a; len];
+        let encoded = BaseHanString::from(BaseHanEncoder::encode_bytes_to_prefixed_string(&data).unwrap());
+        assert_eq!(encoded.char_count(), 1 + basehan::encoded_char_count(len));
+        assert_eq!(encoded.bit_length(), len * 8);
+    }
+
+    let mut encoder = BaseHanEncoder::new().with_line_width(4);
+    let mut wrapped = encoder.update([1; 30]).unwrap();
+    wrapped.extend(encoder.finish_padded(8).unwrap());
+    let wrapped = BaseHanString::from(wrapped);
+    assert_eq!(wrapped.char_count(), 19); // Padded to 24
+    assert_eq!(wrapped.bit_length(), 240);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn into_chars_includes_the_terminator() {
+    use std::io::Write;
+
+    let mut encoder = BaseHanEncoder::new();
+    encoder.write_all(b"all at once").unwrap();
+    let expected: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"all at once").unwrap().chars().collect();
+    assert_eq!(encoder.into_chars(), expected);
+
+    let mut encoder = BaseHanEncoder::new();
+    let mut chars = encoder.update(b"all at ").unwrap();
+    encoder.write_all(b"once").unwrap();
+    chars.extend(encoder.into_chars());
+    assert_eq!(chars, expected);
+
+    assert!(BaseHanEncoder::new().into_chars().is_empty());
+}
+
+#[test]
+fn ring_buffer_encoder_matches_update() {
+    use basehan::v1::RingBufferBaseHanEncoder;
+
+    let data: Vec<u8> = (0..100).collect();
+    let mut ring = RingBufferBaseHanEncoder::new(4);
+    let mut encoded = String::new();
+    for &byte in &data {
+        match ring.try_encode(byte) {
+            Ok(_) => (),
+            Err(BaseHanError::BufferTooSmall(5)) => {
+                encoded.extend(std::iter::from_fn(|| ring.pop()));
+                ring.try_encode(byte).unwrap();
+            }
+            Err(e) => panic!("{}", e),
+        }
+        assert!(ring.len() <= ring.capacity());
+    }
+    encoded.extend(std::iter::from_fn(|| ring.pop()));
+    encoded.push(ring.finish().unwrap());
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+    assert_eq!(ring.finish(), Err(BaseHanError::EmptyInput));
+}
+
+#[test]
+fn split_at_bytes_branches_the_stream() {
+    let data = b"shared prefix|";
+    let (mut left, mut right) = BaseHanEncoder::new().split_at_bytes(data.len(), data).unwrap();
+    let mut left_out = left.update(b"left").unwrap();
+    left_out.extend(left.into_chars());
+    let mut right_out = right.update(b"right branch").unwrap();
+    right_out.extend(right.into_chars());
+    assert_eq!(String::from_iter(left_out), BaseHanEncoder::encode_bytes_to_string(b"shared prefix|left").unwrap());
+    assert_eq!(
+        String::from_iter(right_out),
+        BaseHanEncoder::encode_bytes_to_string(b"shared prefix|right branch").unwrap()
+    );
+
+    assert!(matches!(BaseHanEncoder::new().split_at_bytes(4, b"abc"), Err(BaseHanError::InvalidRange)));
+}
+
+#[test]
+fn builder_combines_settings() {
+    use basehan::v1::BaseHanEncoderBuilder;
+
+    let data: Vec<u8> = (0..40).collect();
+    let mut encoder = BaseHanEncoder::builder()
+        .buffer_size(64)
+        .base_offset(0x10000)
+        .line_width(8)
+        .line_separator("\r\n")
+        .header(true)
+        .build()
+        .unwrap();
+    let mut encoded = String::from_iter(encoder.update(&data).unwrap());
+    encoded.extend(encoder.finish_chars().unwrap());
+    assert!(encoded.starts_with(basehan::v1::VERSION_PREFIX));
+    assert_eq!(encoded.matches("\r\n").count(), 3);
+    let mut decoder = BaseHanDecoder::with_offset(0x10000).unwrap().with_header(true);
+    assert_eq!(decoder.update_str(&encoded).unwrap(), data);
+
+    let mut default = BaseHanEncoderBuilder::new().build().unwrap();
+    let mut out = default.update(&data).unwrap();
+    out.push(default.finish().unwrap());
+    assert_eq!(String::from_iter(out), BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+
+    assert!(matches!(
+        BaseHanEncoder::builder().base_offset(0xd000).build(),
+        Err(BaseHanError::InvalidOffset(0xd000))
+    ));
+}
+
+#[test]
+fn cow_borrows_tiny_inputs() {
+    use std::borrow::Cow;
+
+    for byte in 0..=255u8 {
+        let encoded = BaseHanEncoder::encode_bytes_to_cow::<1>(&[byte]).unwrap();
+        assert!(matches!(encoded, Cow::Borrowed(_)));
+        assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&[byte]).unwrap());
+    }
+    assert_eq!(BaseHanEncoder::encode_bytes_to_cow::<1>(b"").unwrap(), Cow::Borrowed(""));
+    let encoded = BaseHanEncoder::encode_bytes_to_cow::<1>(b"ow").unwrap();
+    assert!(matches!(encoded, Cow::Owned(_)));
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(b"ow").unwrap());
+}
+
+#[test]
+fn cow_threshold_is_configurable() {
+    use std::borrow::Cow;
+
+    for pair in (0..=u16::MAX).step_by(7).chain([u16::MAX]) {
+        let pair = pair.to_be_bytes();
+        let encoded = BaseHanEncoder::encode_bytes_to_cow::<2>(&pair).unwrap();
+        assert!(matches!(encoded, Cow::Borrowed(_)));
+        assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&pair).unwrap());
+    }
+    assert!(matches!(BaseHanEncoder::encode_bytes_to_cow::<2>(b"a").unwrap(), Cow::Borrowed(_)));
+    assert!(matches!(BaseHanEncoder::encode_bytes_to_cow::<2>(b"own").unwrap(), Cow::Owned(_)));
+    // With a threshold of 0 only the empty output is borrowed
+    assert!(matches!(BaseHanEncoder::encode_bytes_to_cow::<0>(b"a").unwrap(), Cow::Owned(_)));
+    assert_eq!(BaseHanEncoder::encode_bytes_to_cow::<0>(b"").unwrap(), Cow::Borrowed(""));
+}
+
+#[test]
+fn encode_const_matches_runtime() {
+    const RAW: &[u8; 30] = b"compile-time encoded resource!";
+    static ENCODED: [char; basehan::encoded_char_count(RAW.len())] = BaseHanEncoder::encode_const(RAW);
+    const EMPTY: [char; 0] = BaseHanEncoder::encode_const(b"");
+
+    assert_eq!(String::from_iter(ENCODED), BaseHanEncoder::encode_bytes_to_string(RAW).unwrap());
+    assert!(EMPTY.is_empty());
+    // 13-byte blocks leave no bits for the terminator
+    let raw: [u8; 26] = core::array::from_fn(|i| (i as u8).wrapping_mul(97));
+    let encoded: [char; 17] = BaseHanEncoder::encode_const(&raw);
+    assert_eq!(String::from_iter(encoded), BaseHanEncoder::encode_bytes_to_string(&raw).unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn spawn_from_reader_sends_all_chars() {
+    let raw: Vec<u8> = (0..=255).cycle().take(3 * 1024 * 1024 + 7).collect();
+    let (handle, receiver) = BaseHanEncoder::spawn_from_reader(std::io::Cursor::new(raw.clone()));
+    let encoded: String = receiver.iter().flatten().collect();
+    handle.join().unwrap().unwrap();
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&raw).unwrap());
+
+    let (handle, receiver) = BaseHanEncoder::spawn_from_reader(std::io::empty());
+    assert_eq!(receiver.iter().count(), 0);
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn shared_encoder_behind_static_mutex() {
+    use std::sync::{LazyLock, Mutex};
+
+    static ENCODER: LazyLock<Mutex<BaseHanEncoder>> = LazyLock::new(|| Mutex::new(BaseHanEncoder::new()));
+
+    // Each thread encodes a whole 13-byte block, which maps to 8 chars whatever the order
+    let blocks: Vec<Vec<char>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4u8)
+            .map(|i| scope.spawn(move || ENCODER.lock().unwrap().update([i; 13]).unwrap()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    for (i, block) in blocks.iter().enumerate() {
+        let mut expected = BaseHanEncoder::encode_bytes_to_string(&[i as u8; 13]).unwrap();
+        expected.pop(); // The terminator
+        assert_eq!(String::from_iter(block), expected);
+    }
+}
+
+#[test]
+fn callback_receives_chars_as_produced() {
+    let raw: Vec<u8> = (0..=255).collect();
+    let mut out = String::new();
+    let mut encoder = BaseHanEncoder::new().with_callback(|c| out.push(c));
+    for chunk in raw.chunks(7) {
+        encoder.update(chunk);
+    }
+    encoder.finish();
+    assert_eq!(out, BaseHanEncoder::encode_bytes_to_string(&raw).unwrap());
+
+    let mut count = 0;
+    BaseHanEncoder::new().with_callback(|_| count += 1).finish();
+    assert_eq!(count, 0);
+
+    let mut encoder = BaseHanEncoder::new();
+    let mut chars = encoder.update(b"finish").unwrap();
+    encoder.finish_with_callback(|c| chars.push(c));
+    assert_eq!(String::from_iter(chars), BaseHanEncoder::encode_bytes_to_string(b"finish").unwrap());
+}
+
+#[test]
+fn update_str_encodes_utf8_bytes() {
+    use basehan::v1::encode_str;
+
+    let text = "UTF-8 文本 ✓";
+    let mut encoder = BaseHanEncoder::new();
+    let mut encoded = encoder.update_str(&text[..4]).unwrap();
+    encoded.extend(encoder.update_str(&text[4..]).unwrap());
+    encoded.push(encoder.finish().unwrap());
+    let expected = BaseHanEncoder::encode_bytes_to_string(text.as_bytes()).unwrap();
+    assert_eq!(String::from_iter(encoded), expected);
+    assert_eq!(encode_str(text).unwrap(), expected);
+    assert_eq!(encode_str("").unwrap(), "");
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&expected).unwrap(), text.as_bytes());
+}
+
+#[test]
+fn update_iter_matches_update() {
+    let data: Vec<u8> = (0..200u8).map(|i| i.wrapping_mul(31)).collect();
+    let mut encoder = BaseHanEncoder::new();
+    // A lazy source, never collected into a slice
+    let mut encoded = encoder.update_iter((0..100u8).map(|i| i.wrapping_mul(31))).unwrap();
+    encoded.extend(encoder.update_iter(data[100..].iter().copied()).unwrap());
+    encoded.push(encoder.finish().unwrap());
+    assert_eq!(String::from_iter(encoded), BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+    assert!(BaseHanEncoder::new().update_iter(core::iter::empty()).unwrap().is_empty());
+}
+
+#[test]
+fn finish_needs_at_least_one_byte() {
+    let mut encoder = BaseHanEncoder::new();
+    assert!(encoder.update(b"").unwrap().is_empty());
+    assert_eq!(encoder.finish(), Err(BaseHanError::EmptyInput));
+
+    // A single zero byte is input too, with a terminator of its own
+    let mut encoder = BaseHanEncoder::new();
+    assert!(encoder.update([0]).unwrap().is_empty());
+    let terminator = encoder.finish().unwrap();
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&terminator.to_string()).unwrap(), [0]);
+}
+
+#[test]
+fn url_safe_output_is_ascii_and_decodes_transparently() {
+    let data: Vec<u8> = (0..50).collect();
+    let mut encoder = BaseHanEncoder::new().url_safe();
+    let mut encoded = encoder.update(&data).unwrap();
+    encoded.extend(encoder.finish_chars().unwrap());
+    let encoded = String::from_iter(encoded);
+    assert!(encoded.chars().all(|c| c == '%' || c.is_ascii_hexdigit()));
+
+    // The percent-encoded UTF-8 of the plain output
+    let plain = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    let escaped: String = plain.bytes().map(|b| format!("%{:02X}", b)).collect();
+    assert_eq!(encoded, escaped);
+
+    // Split anywhere, even within an escape
+    let mut decoder = BaseHanDecoder::new();
+    let mut decoded = decoder.update_str(&encoded[..7]).unwrap();
+    decoded.extend(decoder.update_str(&encoded[7..]).unwrap());
+    assert_eq!(decoded, data);
+    assert_eq!(decoder.finish(), Ok(None));
+
+    // Malformed escapes: not hex, no UTF-8 lead byte, a char cut short
+    assert!(BaseHanDecoder::new().update_str("%E4%B8%8G").is_err());
+    assert!(BaseHanDecoder::new().update_str("%FF%80%80").is_err());
+    let mut decoder = BaseHanDecoder::new();
+    decoder.update_str("%E4%B8").unwrap();
+    assert_eq!(decoder.finish(), Err(BaseHanError::TruncatedStream));
+}
@@ -0,0 +1,88 @@
+use basehan::v1;
+use basehan::BaseHanError;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+#[cfg(feature = "std")]
+fn read_and_encode<R: Read>(mut input: R) -> Result<String, BaseHanError> {
+    let mut buf = Vec::new();
+    input.read_to_end(&mut buf)?;
+    basehan::encode(buf)
+}
+
+#[cfg(feature = "std")]
+struct Broken;
+
+#[cfg(feature = "std")]
+impl Read for Broken {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken"))
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_errors_convert_with_question_mark() {
+    assert_eq!(read_and_encode(&b"abc"[..]), basehan::encode(b"abc"));
+    let err = read_and_encode(Broken).unwrap_err();
+    assert_eq!(err, BaseHanError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "broken")));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn errors_clone_and_compare_by_value() {
+    let errors = [
+        v1::BaseHanError::EndOfFile,
+        v1::BaseHanError::InvalidOffset(0xd800),
+        v1::BaseHanError::ExceedsTarget(7),
+        v1::BaseHanError::InvalidCode(0x41, 2),
+        v1::BaseHanError::CodeOutOfRange { code: 0x41, pos: 2, valid_min: 0x4e00, valid_max: 0x8dff },
+    ];
+    for (i, a) in errors.iter().enumerate() {
+        assert_eq!(a.clone(), *a);
+        for b in &errors[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    assert_ne!(v1::BaseHanError::InvalidCode(0x41, 2), v1::BaseHanError::InvalidCode(0x41, 3));
+    let internal = BaseHanError::InternalError(String::from("internal"));
+    assert_eq!(internal.clone(), internal);
+    assert_ne!(internal, BaseHanError::InvalidCode(0x41, 2));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_errors_clone_and_compare_by_kind_and_message() {
+    let err = v1::BaseHanError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "broken"));
+    assert_eq!(err.clone(), err);
+    assert_ne!(err, v1::BaseHanError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "other")));
+    assert_ne!(err, v1::BaseHanError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "broken")));
+}
+
+#[test]
+fn errors_display_readable_messages() {
+    assert_eq!(v1::BaseHanError::InvalidCode(0x41, 2).to_string(), "invalid code 0x41 at pos 2");
+    assert_eq!(v1::BaseHanError::TruncatedStream.to_string(), "input ends before the terminator");
+    assert_eq!(
+        v1::BaseHanError::CodeOutOfRange { code: 0x41, pos: 0, valid_min: 0x4e00, valid_max: 0x8dff }.to_string(),
+        "code 0x41 at pos 0 out of the valid range 0x4e00..=0x8dff"
+    );
+    assert_eq!(BaseHanError::InternalError(String::from("oops")).to_string(), "internal error: oops");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn errors_box_into_dyn_error() {
+    fn decode(input: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(v1::BaseHanDecoder::decode_str_to_bytes(input)?)
+    }
+
+    let err = decode("\u{4e00}").unwrap_err();
+    assert_eq!(err.to_string(), "input ends before the terminator");
+    assert!(err.source().is_none());
+    assert_eq!(err.downcast_ref::<v1::BaseHanError>(), Some(&v1::BaseHanError::TruncatedStream));
+
+    let io_err = v1::BaseHanError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "broken"));
+    let source = std::error::Error::source(&io_err).unwrap();
+    assert_eq!(source.to_string(), "broken");
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "ffi")]
+
+use basehan::ffi::*;
+use std::ptr;
+
+fn call(
+    f: unsafe extern "C" fn(*const u8, usize, *mut u8, *mut usize) -> i32,
+    input: &[u8],
+) -> Result<Vec<u8>, i32> {
+    // Query the size first
+    let mut len = 0;
+    let code = unsafe { f(input.as_ptr(), input.len(), ptr::null_mut(), &mut len) };
+    if code != BASEHAN_OK && code != BASEHAN_BUFFER_TOO_SMALL {
+        return Err(code);
+    }
+    let mut out = vec![0u8; len];
+    match unsafe { f(input.as_ptr(), input.len(), out.as_mut_ptr(), &mut len) } {
+        BASEHAN_OK => Ok(out),
+        code => Err(code),
+    }
+}
+
+#[test]
+fn round_trip() {
+    for len in 0..=26 {
+        let data: Vec<u8> = (0..len).map(|i| i as u8 ^ 0xc3).collect();
+        let encoded = call(basehan_encode, &data).unwrap();
+        assert_eq!(call(basehan_decode, &encoded).unwrap(), data);
+    }
+}
+
+#[test]
+fn error_codes() {
+    let mut len = 16;
+    let mut out = [0u8; 16];
+    assert_eq!(
+        unsafe { basehan_encode(ptr::null(), 1, out.as_mut_ptr(), &mut len) },
+        BASEHAN_NULL_POINTER
+    );
+    assert_eq!(
+        unsafe { basehan_encode(b"a".as_ptr(), 1, out.as_mut_ptr(), ptr::null_mut()) },
+        BASEHAN_NULL_POINTER
+    );
+    let mut len = 1;
+    assert_eq!(
+        unsafe { basehan_encode(b"abc".as_ptr(), 3, out.as_mut_ptr(), &mut len) },
+        BASEHAN_BUFFER_TOO_SMALL
+    );
+    assert_eq!(len, basehan::encoded_char_count(3) * 3); // 3 bytes per char in UTF-8
+    assert_eq!(call(basehan_decode, b"\xff"), Err(BASEHAN_INVALID_UTF8));
+    assert_eq!(call(basehan_decode, b"abc"), Err(BASEHAN_INVALID_INPUT));
+}
+
+#[test]
+fn decode_rejects_incomplete_streams() {
+    let encoded = call(basehan_encode, b"complete").unwrap();
+    assert_eq!(call(basehan_decode, &encoded[..encoded.len() - 3]), Err(BASEHAN_INVALID_INPUT));
+    let mut trailing = encoded.clone();
+    trailing.extend_from_slice("\u{4e00}".as_bytes());
+    assert_eq!(call(basehan_decode, &trailing), Err(BASEHAN_INVALID_INPUT));
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "hex")]
+
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder, BaseHanError};
+
+#[test]
+fn encodes_hex_bytes() {
+    let encoded = BaseHanEncoder::encode_hex_string("DEADbeef00").unwrap();
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&[0xde, 0xad, 0xbe, 0xef, 0x00]).unwrap());
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap(), [0xde, 0xad, 0xbe, 0xef, 0x00]);
+    assert_eq!(BaseHanEncoder::encode_hex_string("").unwrap(), "");
+}
+
+#[test]
+fn rejects_malformed_hex() {
+    for hex in ["abc", "0g", "0x00", "de ad"] {
+        assert_eq!(BaseHanEncoder::encode_hex_string(hex), Err(BaseHanError::InvalidHex), "{}", hex);
+    }
+    assert_eq!(BaseHanError::InvalidHex.to_string(), "invalid hex string");
+}
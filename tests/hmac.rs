@@ -0,0 +1,103 @@
+#![cfg(feature = "hmac")]
+
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder, BaseHanError};
+
+const KEY: &[u8] = b"secret key";
+
+fn encode_with_hmac(data: &[u8], key: &[u8]) -> String {
+    let mut encoder = BaseHanEncoder::new().with_hmac(key);
+    let mut out = String::from_iter(encoder.update(data).unwrap());
+    out.extend(encoder.finish_chars().unwrap());
+    out
+}
+
+fn decode_with_hmac(encoded: &str, key: &[u8]) -> Result<Vec<u8>, BaseHanError> {
+    let mut decoder = BaseHanDecoder::new().with_hmac_verify(key);
+    let out = decoder.update_str(encoded)?;
+    assert_eq!(decoder.finish()?, None);
+    Ok(out)
+}
+
+#[test]
+fn tag_is_8_chars_and_verifies() {
+    let data = b"integrity checked payload";
+    let encoded = encode_with_hmac(data, KEY);
+    let plain = BaseHanEncoder::encode_bytes_to_string(data).unwrap();
+    assert_eq!(encoded.chars().count(), plain.chars().count() + 8);
+    assert!(encoded.starts_with(&plain));
+    assert_eq!(decode_with_hmac(&encoded, KEY).unwrap(), data);
+    // A streaming decoder not verifying stops before the tag, the one-shot one rejects it
+    assert_eq!(BaseHanDecoder::new().update_str(&encoded).unwrap(), data);
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&encoded), Err(BaseHanError::EndOfFile));
+}
+
+#[test]
+fn chars_remaining_estimate_skips_the_tag() {
+    let data = b"tagged input";
+    let encoded: Vec<char> = encode_with_hmac(data, KEY).chars().collect();
+    let mut decoder = BaseHanDecoder::new().with_hmac_verify(KEY);
+    assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(data.len()));
+    // Nothing is left to decode but the tag
+    decoder.update(&encoded[..encoded.len() - 8]).unwrap();
+    assert!(!decoder.is_finished());
+    assert_eq!(decoder.chars_remaining_estimate(encoded.len()), Some(0));
+}
+
+#[test]
+fn tag_mismatch() {
+    let data = b"integrity checked payload";
+    let encoded = encode_with_hmac(data, KEY);
+    assert_eq!(decode_with_hmac(&encoded, b"wrong key"), Err(BaseHanError::IntegrityFailure));
+
+    let mut tampered: Vec<char> = encoded.chars().collect();
+    tampered[0] = char::from_u32(tampered[0] as u32 ^ 1).unwrap();
+    let tampered = String::from_iter(tampered);
+    assert_eq!(decode_with_hmac(&tampered, KEY), Err(BaseHanError::IntegrityFailure));
+
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+    assert_eq!(decode_with_hmac(&truncated, KEY), Err(BaseHanError::TruncatedStream));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn tag_across_chunks_and_streams() {
+    use std::io::Write;
+
+    let mut encoder = BaseHanEncoder::new().with_hmac(KEY);
+    let mut encoded = Vec::new();
+    for message in [&b"first"[..], b"second"] {
+        encoder.write_all(message).unwrap();
+        encoder.flush().unwrap();
+        encoded.push(String::from_iter(encoder.encoded_chars()));
+    }
+    assert_eq!(encoded[1], encode_with_hmac(b"second", KEY));
+
+    let chars: Vec<char> = encoded[0].chars().collect();
+    let mut decoder = BaseHanDecoder::new().with_hmac_verify(KEY);
+    let mut out = Vec::new();
+    for chunk in chars.chunks(3) {
+        out.extend(decoder.update(chunk).unwrap());
+    }
+    assert!(decoder.is_finished());
+    assert_eq!(decoder.finish(), Ok(None));
+    assert_eq!(out, b"first");
+}
+
+#[test]
+fn encode_repeat_feeds_the_mac() {
+    let mut encoder = BaseHanEncoder::new().with_hmac(KEY);
+    let mut encoded = String::from_iter(encoder.encode_repeat(0, 100).unwrap());
+    encoded.extend(encoder.finish_chars().unwrap());
+    assert_eq!(encoded, encode_with_hmac(&[0; 100], KEY));
+}
+
+#[test]
+fn multi_message_verifies_each_tag() {
+    let concatenated = format!("{}{}", encode_with_hmac(b"first", KEY), encode_with_hmac(b"second", KEY));
+    let mut decoder = BaseHanDecoder::new().with_hmac_verify(KEY).with_multi_message(true);
+    assert_eq!(decoder.update_messages(concatenated.chars()).unwrap(), [&b"first"[..], b"second"]);
+
+    let tampered = format!("{}{}", encode_with_hmac(b"first", KEY), encode_with_hmac(b"second", b"other key"));
+    let mut decoder = BaseHanDecoder::new().with_hmac_verify(KEY).with_multi_message(true);
+    assert_eq!(decoder.update_messages(tampered.chars()), Err(BaseHanError::IntegrityFailure));
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "log")]
+
+use basehan::v1::BaseHanEncoder;
+use log::{Level, Log, Metadata, Record};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Count the warnings, without formatting them.
+struct WarnCounter(AtomicUsize);
+
+impl Log for WarnCounter {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if record.level() == Level::Warn {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static WARNINGS: WarnCounter = WarnCounter(AtomicUsize::new(0));
+
+#[test]
+fn decode_lossy_warns_for_each_dropped_char() {
+    log::set_logger(&WARNINGS).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"warn").unwrap();
+    assert_eq!(basehan::decode_lossy(&encoded), b"warn");
+    assert_eq!(WARNINGS.0.load(Ordering::SeqCst), 0);
+
+    let noisy: String = encoded.chars().flat_map(|c| ['#', c]).collect();
+    assert_eq!(basehan::decode_lossy(&noisy), b"warn");
+    assert_eq!(WARNINGS.0.load(Ordering::SeqCst), encoded.chars().count());
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "rayon")]
+
+use basehan::parallel_encode;
+use basehan::v1::BaseHanEncoder;
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn parallel_matches_sequential(data in proptest::collection::vec(any::<u8>(), 0..2000), threads in 0usize..6) {
+        prop_assert_eq!(
+            parallel_encode(&data, threads).unwrap(),
+            BaseHanEncoder::encode_bytes_to_string(&data).unwrap()
+        );
+    }
+}
+
+#[test]
+fn aligned_lengths() {
+    for len in [13, 26, 13 * 64, 13 * 64 + 1] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        assert_eq!(
+            parallel_encode(&data, 4).unwrap(),
+            BaseHanEncoder::encode_bytes_to_string(&data).unwrap()
+        );
+    }
+}
+
+#[test]
+fn blocks_larger_than_a_chunk() {
+    // Several chunks of 13 KiB per thread, on a dedicated pool and on the global one
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let expected = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    for threads in [0, 1, 3] {
+        assert_eq!(parallel_encode(&data, threads).unwrap(), expected, "{} threads", threads);
+    }
+}
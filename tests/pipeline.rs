@@ -0,0 +1,50 @@
+#![cfg(any(feature = "flate2", feature = "zstd"))]
+
+use basehan::pipeline::{BaseHanPipeline, CompressionAlgorithm};
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+use std::io::Write;
+
+fn run(pipeline: BaseHanPipeline, data: &[u8]) -> String {
+    let mut encoding = pipeline.encode().unwrap();
+    for chunk in data.chunks(100) {
+        encoding.write_all(chunk).unwrap();
+        encoding.flush().unwrap();
+    }
+    encoding.finish().unwrap()
+}
+
+fn sample() -> Vec<u8> {
+    b"a fairly repetitive payload, ".repeat(50)
+}
+
+#[test]
+fn plain_pipeline_matches_the_encoder() {
+    let data = sample();
+    // Flushing in between does not end the stream
+    assert_eq!(run(BaseHanPipeline::new(), &data), BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+    assert_eq!(run(BaseHanPipeline::new(), b""), "");
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn gzip_then_encode() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let data = sample();
+    let encoded = run(BaseHanPipeline::new().compress(CompressionAlgorithm::Gzip), &data);
+    assert!(encoded.chars().count() < BaseHanEncoder::encode_bytes_to_string(&data).unwrap().chars().count());
+    let compressed = BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap();
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&compressed[..]).read_to_end(&mut decompressed).unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_then_encode() {
+    let data = sample();
+    let encoded = run(BaseHanPipeline::new().compress(CompressionAlgorithm::Zstd), &data);
+    let compressed = BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap();
+    assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), data);
+}
@@ -0,0 +1,455 @@
+use basehan::v1::{BaseHanCodec, BaseHanDecoder, BaseHanEncoder, BaseHanError, BaseHanPair, BaseHanString, VERSION_PREFIX};
+use proptest::prelude::*;
+
+fn encode_in_chunks(data: &[u8], chunk_size: usize) -> String {
+    let mut encoder = BaseHanEncoder::new();
+    let mut out = String::new();
+    for chunk in data.chunks(chunk_size) {
+        out.extend(encoder.update(chunk).unwrap());
+    }
+    out.extend(encoder.finish().ok());
+    out
+}
+
+fn decode_in_chunks(encoded: &str, chunk_size: usize) -> Vec<u8> {
+    let chars: Vec<char> = encoded.chars().collect();
+    let mut decoder = BaseHanDecoder::new();
+    let mut out = Vec::new();
+    for chunk in chars.chunks(chunk_size) {
+        out.extend(decoder.update(chunk).unwrap());
+    }
+    match decoder.finish() {
+        Ok(byte) => assert_eq!(byte, None),
+        Err(e) => assert!(encoded.is_empty(), "{}", e), // Empty input has no terminator
+    }
+    out
+}
+
+#[test]
+fn v0_decode_takes_str_and_string() {
+    let encoded: String = basehan::encode(b"v0 payload").unwrap();
+    assert_eq!(basehan::decode(&encoded).unwrap(), b"v0 payload");
+    assert_eq!(basehan::decode(encoded.as_str()).unwrap(), b"v0 payload");
+}
+
+#[test]
+fn round_trip_every_length_mod_13() {
+    // Cover every remaining bits count of the terminator, twice
+    for len in 0..=26 {
+        let data: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8 | 0x80).collect();
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        assert_eq!(BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap(), data, "len {}", len);
+    }
+}
+
+proptest! {
+    #[test]
+    fn round_trip(data in proptest::collection::vec(any::<u8>(), 0..1024)) {
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        prop_assert_eq!(BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn chunked_encode_matches_one_shot(
+        data in proptest::collection::vec(any::<u8>(), 0..1024),
+        chunk_size in 1usize..64,
+    ) {
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        prop_assert_eq!(encode_in_chunks(&data, chunk_size), encoded);
+    }
+
+    #[test]
+    fn chunked_decode_matches_one_shot(
+        data in proptest::collection::vec(any::<u8>(), 0..1024),
+        chunk_size in 1usize..64,
+    ) {
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        prop_assert_eq!(decode_in_chunks(&encoded, chunk_size), data);
+    }
+}
+
+#[test]
+fn empty_input() {
+    assert_eq!(BaseHanEncoder::new().finish(), Err(BaseHanError::EmptyInput));
+    assert_eq!(BaseHanEncoder::encode_bytes_to_string(b"").unwrap(), "");
+
+    // A lone terminator without remaining bits is an empty message too
+    let mut decoder = BaseHanDecoder::new();
+    assert_eq!(decoder.update_str("\u{6e01}").unwrap(), vec![]);
+    assert_eq!(decoder.finish(), Ok(None));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn empty_file() {
+    let mut encoded = Vec::new();
+    assert_eq!(BaseHanEncoder::new().encode_file(&b""[..], &mut encoded).unwrap(), 0);
+    assert!(encoded.is_empty());
+
+    let mut decoded = Vec::new();
+    assert_eq!(BaseHanDecoder::new().decode_file(&b""[..], &mut decoded).unwrap(), 0);
+    assert!(decoded.is_empty());
+
+    let mut decoded = Vec::new();
+    BaseHanDecoder::new().decode_file("\u{6e01}".as_bytes(), &mut decoded).unwrap();
+    assert!(decoded.is_empty());
+}
+
+#[test]
+fn custom_offset_round_trip() {
+    let data: Vec<u8> = (0..=255).collect();
+    for offset in [0x3400, 0x8e00, 0x20000] {
+        let mut encoder = BaseHanEncoder::with_offset(offset).unwrap();
+        let mut encoded = encoder.update(&data).unwrap();
+        let terminator = encoder.finish().unwrap();
+        assert!(encoded.iter().all(|&c| (offset..offset + 0x2000).contains(&(c as u32))));
+        // Only the data chars move, the terminator does not
+        assert_eq!(terminator, BaseHanEncoder::encode_bytes_to_string(&data).unwrap().chars().last().unwrap());
+        encoded.push(terminator);
+
+        let mut decoder = BaseHanDecoder::with_offset(offset).unwrap();
+        assert_eq!(decoder.update(&encoded).unwrap(), data, "offset {:#x}", offset);
+        assert!(decoder.is_finished());
+        assert!(BaseHanDecoder::new().update(&encoded).is_err());
+    }
+    assert!(matches!(BaseHanEncoder::with_offset(0xd000), Err(BaseHanError::InvalidOffset(0xd000))));
+    assert!(matches!(BaseHanDecoder::with_offset(0x6000), Err(BaseHanError::InvalidOffset(0x6000))));
+}
+
+fn leak_alphabet(f: impl Fn(u32) -> u32) -> &'static [char; 8192] {
+    Box::leak(Box::new(core::array::from_fn(|i| char::from_u32(f(i as u32)).unwrap())))
+}
+
+#[test]
+fn custom_alphabet_round_trip() {
+    // Every other char of CJK Extension B
+    let alphabet = leak_alphabet(|i| 0x20000 + 2 * i);
+    let data: Vec<u8> = (0..=255).rev().collect();
+    let mut encoder = BaseHanEncoder::with_alphabet(alphabet).unwrap();
+    let mut encoded = encoder.update(&data).unwrap();
+    assert!(encoded.iter().all(|c| alphabet.binary_search(c).is_ok()));
+    encoded.push(encoder.finish().unwrap());
+
+    let mut decoder = BaseHanDecoder::with_alphabet(alphabet).unwrap();
+    assert_eq!(decoder.update(&encoded).unwrap(), data);
+    assert!(decoder.is_finished());
+    assert!(BaseHanDecoder::new().update(&encoded).is_err());
+
+    // Unsorted, or clashing with the terminators or skipped whitespace
+    let unsorted = leak_alphabet(|i| 0x24000 - 2 * i);
+    let terminators = leak_alphabet(|i| 0x6000 + i);
+    let whitespace = leak_alphabet(|i| if i == 0 { ' ' as u32 } else { 0x20000 + i });
+    for alphabet in [unsorted, terminators, whitespace] {
+        assert!(matches!(BaseHanEncoder::with_alphabet(alphabet), Err(BaseHanError::InvalidAlphabet)));
+        assert!(matches!(BaseHanDecoder::with_alphabet(alphabet), Err(BaseHanError::InvalidAlphabet)));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn file_round_trip_across_chunk_sizes() {
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let expected = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    // Chunks of 1 and 2 bytes split every UTF-8 char
+    for chunk_size in [1, 2, 5, 4096] {
+        let mut encoded = Vec::new();
+        let read = BaseHanEncoder::new().with_chunk_size(chunk_size).encode_file(&data[..], &mut encoded).unwrap();
+        assert_eq!(read, data.len() as u64);
+        assert_eq!(encoded, expected.as_bytes(), "chunk size {}", chunk_size);
+
+        let mut decoded = Vec::new();
+        let read = BaseHanDecoder::new().with_chunk_size(chunk_size).decode_file(&encoded[..], &mut decoded).unwrap();
+        assert_eq!(read, encoded.len() as u64);
+        assert_eq!(decoded, data, "chunk size {}", chunk_size);
+    }
+
+    let truncated = &expected.as_bytes()[..expected.len() - 3];
+    let result = BaseHanDecoder::new().decode_file(truncated, &mut Vec::new());
+    assert!(matches!(result, Err(BaseHanError::TruncatedStream)));
+    let split_char = &expected.as_bytes()[..expected.len() - 1];
+    assert!(BaseHanDecoder::new().decode_file(split_char, &mut Vec::new()).is_err());
+}
+
+#[test]
+fn update_into_and_decode_into() {
+    let data: Vec<u8> = (0..=255).collect();
+    let mut encoder = BaseHanEncoder::new();
+    let mut encoded = String::from("prefix ");
+    let mut nchars = 0;
+    for chunk in data.chunks(10) {
+        nchars += encoder.update_into(chunk, &mut encoded).unwrap();
+    }
+    encoded.push(encoder.finish().unwrap());
+    let expected = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+    assert_eq!(encoded, format!("prefix {}", expected));
+    assert_eq!(nchars + 1, expected.chars().count());
+
+    let chars: Vec<char> = expected.chars().collect();
+    let mut decoder = BaseHanDecoder::new();
+    let mut decoded = vec![0xff];
+    let mut nbytes = 0;
+    for chunk in chars.chunks(7) {
+        nbytes += decoder.decode_into(chunk, &mut decoded).unwrap();
+    }
+    assert_eq!(nbytes, data.len());
+    assert_eq!(decoded[1..], data[..]);
+}
+
+#[test]
+fn header() {
+    let mut encoder = BaseHanEncoder::new().with_header(true);
+    let mut encoded = String::from_iter(encoder.update(b"with").unwrap());
+    encoded.extend(encoder.update(b" header").unwrap());
+    encoded.push(encoder.finish().unwrap());
+    let plain = BaseHanEncoder::encode_bytes_to_string(b"with header").unwrap();
+    assert_eq!(encoded, format!("{}{}", VERSION_PREFIX, plain));
+
+    let mut decoder = BaseHanDecoder::new().with_header(true);
+    assert_eq!(decoder.update_str(&format!(" {}", encoded)).unwrap(), b"with header");
+    assert_eq!(decoder.finish(), Ok(None));
+    // The header is optional, and only accepted at the start
+    let mut decoder = BaseHanDecoder::new().with_header(true);
+    assert_eq!(decoder.update_str(&plain).unwrap(), b"with header");
+    let mut decoder = BaseHanDecoder::new().with_header(true);
+    let misplaced = format!("{}{}{}", &plain[..3], VERSION_PREFIX, &plain[3..]);
+    assert!(decoder.update_str(&misplaced).is_err());
+    assert_eq!(BaseHanDecoder::auto_detect(&encoded).unwrap(), b"with header");
+
+    assert!(BaseHanEncoder::new().with_header(true).update(b"").unwrap().is_empty());
+}
+
+#[test]
+fn clone_checkpoints_the_stream() {
+    let mut encoder = BaseHanEncoder::new();
+    let prefix = String::from_iter(encoder.update(b"common prefix, ").unwrap());
+    for suffix in [&b"first suffix"[..], b"second"] {
+        let mut branch = encoder.clone();
+        let mut encoded = prefix.clone();
+        encoded.extend(branch.update(suffix).unwrap());
+        encoded.push(branch.finish().unwrap());
+        let data = [&b"common prefix, "[..], suffix].concat();
+        assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&data).unwrap());
+    }
+
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"decoded twice").unwrap();
+    let chars: Vec<char> = encoded.chars().collect();
+    let mut decoder = BaseHanDecoder::new();
+    let head = decoder.update(&chars[..3]).unwrap();
+    let mut branch = decoder.clone();
+    for decoder in [&mut decoder, &mut branch] {
+        let mut decoded = head.clone();
+        decoded.extend(decoder.update(&chars[3..]).unwrap());
+        assert_eq!(decoded, b"decoded twice");
+    }
+}
+
+#[test]
+fn chunked_messages() {
+    let payloads: [&[u8]; 4] = [b"first", b"", b"third payload", b"\xff"];
+    let encoded = BaseHanEncoder::encode_chunked(&payloads, '|').unwrap();
+    assert_eq!(encoded.matches('|').count(), 3);
+    assert_eq!(BaseHanDecoder::decode_chunked(&encoded, '|').unwrap(), payloads);
+
+    assert_eq!(
+        BaseHanEncoder::encode_chunked(&payloads, '\u{4e00}'),
+        Err(BaseHanError::InvalidCode(0x4e00, 0))
+    );
+    assert_eq!(
+        BaseHanEncoder::encode_chunked(&payloads, ' '),
+        Err(BaseHanError::InvalidCode(' ' as u32, 0))
+    );
+    // Every segment must be complete
+    let truncated = &encoded[..encoded.rfind('|').unwrap() - '\u{6e00}'.len_utf8()];
+    assert_eq!(
+        BaseHanDecoder::decode_chunked(truncated, '|'),
+        Err(BaseHanError::TruncatedStream)
+    );
+    // Nothing may follow a segment's terminator, as with the wrong delimiter
+    let encoded = BaseHanEncoder::encode_chunked(&payloads, ';').unwrap();
+    assert_eq!(
+        BaseHanDecoder::decode_chunked(&encoded, ','),
+        Err(BaseHanError::EndOfFile)
+    );
+}
+
+#[test]
+fn codec_round_trips_from_a_clean_state() {
+    let mut codec = BaseHanCodec::new();
+    // Leftovers of half-fed streams do not leak into the round trip
+    codec.encoder().update(b"half").unwrap();
+    codec.decoder().update_str("\u{4e00}").unwrap();
+    assert_eq!(codec.round_trip(b"clean"), Ok(true));
+    for data in [&b""[..], b"x", b"thirteen byte", &[0xff; 100]] {
+        assert_eq!(codec.round_trip(data), Ok(true), "{:?}", data);
+    }
+
+    let mut codec = BaseHanCodec::with_offset(0x3400).unwrap();
+    assert_eq!(codec.round_trip(b"offset"), Ok(true));
+    assert!(BaseHanCodec::with_offset(0xd800).is_err());
+}
+
+#[test]
+fn pair_stays_consistent() {
+    let pair = BaseHanPair::from_raw(b"pair".to_vec()).unwrap();
+    assert!(pair.verify());
+    assert_eq!(BaseHanPair::from_encoded(&pair.encoded), Ok(pair.clone()));
+
+    let mut tampered = pair.clone();
+    tampered.raw.push(0);
+    assert!(!tampered.verify());
+
+    let truncated: String = pair.encoded.chars().take(2).collect();
+    assert_eq!(BaseHanPair::from_encoded(&truncated), Err(BaseHanError::TruncatedStream));
+    assert!(BaseHanPair::from_raw(Vec::new()).unwrap().verify());
+
+    // A second message behind the first one is not part of the pair
+    let mut concatenated = pair.clone();
+    concatenated.encoded.push_str(&pair.encoded);
+    assert!(!concatenated.verify());
+    assert_eq!(BaseHanPair::from_encoded(&concatenated.encoded), Err(BaseHanError::EndOfFile));
+}
+
+#[cfg(all(unix, feature = "std"))]
+#[test]
+fn os_str_round_trip() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    let path = Path::new("/tmp/base-han/\u{4e00}.txt");
+    let encoded = basehan::encode_os_str(path.as_os_str()).unwrap();
+    assert_eq!(basehan::decode_to_os_string(&encoded).unwrap(), path.as_os_str());
+
+    let not_utf8 = OsStr::from_bytes(b"\xff\xfe/name");
+    let encoded = basehan::encode_os_str(not_utf8).unwrap();
+    assert_eq!(basehan::decode_to_os_string(&encoded).unwrap(), not_utf8);
+    assert_eq!(basehan::decode_to_os_string("").unwrap(), OsStr::new(""));
+}
+
+#[test]
+fn metadata_round_trip() {
+    for (data, meta) in [(&b"payload"[..], &b"text/plain"[..]), (b"", b""), (b"no meta", b""), (b"", b"meta only")] {
+        let encoded = BaseHanEncoder::encode_with_metadata(data, meta).unwrap();
+        assert_eq!(
+            BaseHanDecoder::decode_with_metadata(&encoded).unwrap(),
+            (data.to_vec(), meta.to_vec())
+        );
+    }
+
+    let too_short = BaseHanEncoder::encode_bytes_to_string(b"abc").unwrap();
+    assert_eq!(BaseHanDecoder::decode_with_metadata(&too_short), Err(BaseHanError::InvalidRange));
+    let overlong = BaseHanEncoder::encode_bytes_to_string(&[0, 0, 0, 9, b'x']).unwrap();
+    assert_eq!(BaseHanDecoder::decode_with_metadata(&overlong), Err(BaseHanError::InvalidRange));
+    let encoded = BaseHanEncoder::encode_with_metadata(b"payload", b"meta").unwrap();
+    assert_eq!(BaseHanDecoder::decode_with_metadata(&(encoded + "\u{4e00}")), Err(BaseHanError::EndOfFile));
+}
+
+#[test]
+fn boolean_array_keeps_bit_count() {
+    let bits: Vec<bool> = (0..100u32).map(|i| i.count_ones() % 3 == 1).collect();
+    for len in 0..bits.len() {
+        let encoded = BaseHanEncoder::encode_boolean_array(&bits[..len]).unwrap();
+        assert_eq!(BaseHanString::from(encoded.clone()).bit_length(), len);
+        assert_eq!(BaseHanDecoder::decode_boolean_array(&encoded).unwrap(), &bits[..len]);
+    }
+
+    // Whole bytes of bits encode like the bytes
+    let bits: Vec<bool> = b"bits".iter().flat_map(|&b| (0..8).rev().map(move |i| b >> i & 1 == 1)).collect();
+    let encoded = BaseHanEncoder::encode_boolean_array(&bits).unwrap();
+    assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(b"bits").unwrap());
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&encoded).unwrap(), b"bits");
+
+    assert_eq!(BaseHanDecoder::decode_boolean_array(&encoded[..6]), Err(BaseHanError::TruncatedStream));
+    assert_eq!(BaseHanDecoder::decode_boolean_array(&(encoded.clone() + "\u{4e00}")), Err(BaseHanError::EndOfFile));
+}
+
+#[test]
+fn length_prefixed_round_trip() {
+    for data in [&b"framed payload"[..], b"", &[0xff; 300]] {
+        let encoded = BaseHanEncoder::encode_length_prefixed(data).unwrap();
+        let mut expected = (data.len() as u32).to_be_bytes().to_vec();
+        expected.extend_from_slice(data);
+        assert_eq!(encoded, BaseHanEncoder::encode_bytes_to_string(&expected).unwrap());
+        assert_eq!(BaseHanDecoder::decode_length_prefixed(&encoded).unwrap(), data);
+    }
+
+    let too_short = BaseHanEncoder::encode_bytes_to_string(&[0, 0, 0]).unwrap();
+    assert_eq!(BaseHanDecoder::decode_length_prefixed(&too_short), Err(BaseHanError::InvalidRange));
+    let mismatched = BaseHanEncoder::encode_bytes_to_string(&[0, 0, 0, 2, b'x']).unwrap();
+    assert_eq!(BaseHanDecoder::decode_length_prefixed(&mismatched), Err(BaseHanError::InvalidRange));
+    let encoded = BaseHanEncoder::encode_length_prefixed(b"cut").unwrap();
+    let truncated: String = encoded.chars().take(3).collect();
+    assert_eq!(BaseHanDecoder::decode_length_prefixed(&truncated), Err(BaseHanError::TruncatedStream));
+    let junk = encoded.clone() + "\u{4e00}";
+    assert_eq!(BaseHanDecoder::decode_length_prefixed(&junk), Err(BaseHanError::EndOfFile));
+    let twice = encoded.repeat(2);
+    assert_eq!(BaseHanDecoder::decode_length_prefixed(&twice), Err(BaseHanError::EndOfFile));
+}
+
+#[test]
+fn default_matches_new() {
+    #[derive(Default)]
+    struct Codecs {
+        encoder: BaseHanEncoder,
+        decoder: BaseHanDecoder,
+        codec: BaseHanCodec,
+    }
+
+    let mut codecs = Codecs::default();
+    let mut encoded = codecs.encoder.update(b"by default").unwrap();
+    encoded.push(codecs.encoder.finish().unwrap());
+    assert_eq!(String::from_iter(&encoded), BaseHanEncoder::encode_bytes_to_string(b"by default").unwrap());
+    assert_eq!(codecs.decoder.update(&encoded).unwrap(), b"by default");
+    assert_eq!(codecs.codec.round_trip(b"by default"), Ok(true));
+}
+
+#[test]
+fn u16_round_trip_in_both_byte_orders() {
+    let samples: Vec<u16> = (0..40u16).map(|i| i.wrapping_mul(0x9e37)).collect();
+    let (head, tail) = samples.split_at(13);
+
+    let mut encoder = BaseHanEncoder::new();
+    let mut le = encoder.update_u16_le(head).unwrap();
+    le.extend(encoder.update_u16_le(tail).unwrap());
+    le.push(encoder.finish().unwrap());
+    let le = String::from_iter(le);
+    let le_bytes: Vec<u8> = samples.iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(le, BaseHanEncoder::encode_bytes_to_string(&le_bytes).unwrap());
+    assert_eq!(BaseHanDecoder::decode_to_u16_le(&le).unwrap(), samples);
+
+    let mut encoder = BaseHanEncoder::new();
+    let mut be = encoder.update_u16_be(&samples).unwrap();
+    be.push(encoder.finish().unwrap());
+    let be = String::from_iter(be);
+    assert_eq!(BaseHanDecoder::decode_to_u16_be(&be).unwrap(), samples);
+    // The byte order is not recorded, reading in the other one swaps every value
+    let swapped: Vec<u16> = samples.iter().map(|v| v.swap_bytes()).collect();
+    assert_eq!(BaseHanDecoder::decode_to_u16_le(&be).unwrap(), swapped);
+
+    let odd = BaseHanEncoder::encode_bytes_to_string(b"odd").unwrap();
+    assert_eq!(BaseHanDecoder::decode_to_u16_le(&odd), Err(BaseHanError::OddLength(3)));
+}
+
+#[test]
+fn reset_starts_a_new_stream_with_the_same_settings() {
+    let mut encoder = BaseHanEncoder::with_offset(0x3400).unwrap().with_line_width(5);
+    encoder.update(b"abandoned halfway").unwrap();
+    encoder.reset();
+    let mut encoded = encoder.update(b"second stream").unwrap();
+    encoded.push(encoder.finish().unwrap());
+
+    let mut fresh = BaseHanEncoder::with_offset(0x3400).unwrap().with_line_width(5);
+    let mut expected = fresh.update(b"second stream").unwrap();
+    expected.push(fresh.finish().unwrap());
+    assert_eq!(encoded, expected);
+
+    let mut decoder = BaseHanDecoder::with_offset(0x3400).unwrap();
+    decoder.update(&encoded[..3]).unwrap();
+    assert!(decoder.update_str("\u{6000}").is_err());
+    assert_eq!(decoder.reset().state(), basehan::v1::DecoderState::Streaming);
+    assert_eq!(decoder.update(&encoded).unwrap(), b"second stream");
+    // A finished decoder takes a new stream after a reset
+    decoder.reset();
+    assert_eq!(decoder.update(&encoded).unwrap(), b"second stream");
+    assert_eq!(decoder.finish(), Ok(None));
+}
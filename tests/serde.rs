@@ -0,0 +1,42 @@
+#![cfg(feature = "serde")]
+
+use basehan::v1::{BaseHanEncoder, BaseHanError};
+use serde::de::value::{Error, StrDeserializer};
+use serde::de::Error as _;
+
+fn deserialize(s: &str) -> Result<Vec<u8>, Error> {
+    basehan::serde::deserialize(StrDeserializer::<Error>::new(s))
+}
+
+#[test]
+fn deserializes_whole_streams_only() {
+    let encoded = BaseHanEncoder::encode_bytes_to_string(b"serde").unwrap();
+    assert_eq!(deserialize(&encoded).unwrap(), b"serde");
+
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+    assert_eq!(deserialize(&truncated), Err(Error::custom(BaseHanError::TruncatedStream)));
+    assert_eq!(deserialize(&(encoded + "\u{4e00}")), Err(Error::custom(BaseHanError::EndOfFile)));
+}
+
+#[test]
+fn field_and_wrapper_round_trip() {
+    use basehan::serde::SerdeBaseHan;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        #[serde(with = "basehan::serde")]
+        payload: Vec<u8>,
+        wrapped: SerdeBaseHan,
+    }
+
+    let message = Message { payload: b"field".to_vec(), wrapped: SerdeBaseHan(Vec::new()) };
+    let json = serde_json::to_string(&message).unwrap();
+    let expected = format!(
+        r#"{{"payload":"{}","wrapped":""}}"#,
+        BaseHanEncoder::encode_bytes_to_string(b"field").unwrap()
+    );
+    assert_eq!(json, expected);
+    assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+    assert!(serde_json::from_str::<Message>(r#"{"payload":"abc","wrapped":""}"#).is_err());
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "simd")]
+
+use basehan::v1::BaseHanEncoder;
+use proptest::prelude::*;
+
+// Line wrapping keeps the byte-by-byte loop, which never wraps this wide
+fn scalar_encoder() -> BaseHanEncoder {
+    BaseHanEncoder::new().with_line_width(usize::MAX)
+}
+
+proptest! {
+    #[test]
+    fn bulk_matches_byte_by_byte(data in proptest::collection::vec(any::<u8>(), 0..2000), chunk_size in 1usize..100) {
+        let mut bulk = BaseHanEncoder::new();
+        let mut scalar = scalar_encoder();
+        let mut bulk_out = Vec::new();
+        let mut scalar_out = Vec::new();
+        for chunk in data.chunks(chunk_size) {
+            bulk_out.extend(bulk.update(chunk).unwrap());
+            scalar_out.extend(scalar.update(chunk).unwrap());
+        }
+        prop_assert_eq!(bulk_out, scalar_out);
+        prop_assert_eq!(bulk.into_chars(), scalar.into_chars());
+    }
+}
+
+#[test]
+fn every_block_alignment_and_header() {
+    let data: Vec<u8> = (0..=255).cycle().take(13 * 40 + 12).collect();
+    for start in 0..13 {
+        let mut bulk = BaseHanEncoder::new().with_header(true);
+        let mut scalar = scalar_encoder().with_header(true);
+        let mut bulk_out = bulk.update(&data[..start]).unwrap();
+        bulk_out.extend(bulk.update(&data[start..]).unwrap());
+        let mut scalar_out = scalar.update(&data[..start]).unwrap();
+        scalar_out.extend(scalar.update(&data[start..]).unwrap());
+        assert_eq!(bulk_out, scalar_out, "split at {}", start);
+        assert_eq!(bulk.finish().unwrap(), scalar.finish().unwrap());
+    }
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "tracing")]
+
+use basehan::v1::{BaseHanDecoder, BaseHanEncoder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Count the spans and events, without formatting them.
+#[derive(Default)]
+struct Counter {
+    spans: AtomicUsize,
+    events: AtomicUsize,
+}
+
+struct CountingSubscriber(Arc<Counter>);
+
+impl Subscriber for CountingSubscriber {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(self.0.spans.fetch_add(1, Ordering::SeqCst) as u64 + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {
+        self.0.events.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn update_and_finish_are_traced() {
+    let counter = Arc::new(Counter::default());
+    tracing::subscriber::with_default(CountingSubscriber(counter.clone()), || {
+        let mut encoder = BaseHanEncoder::new();
+        let mut encoded = encoder.update(b"traced").unwrap();
+        encoded.push(encoder.finish().unwrap());
+        let mut decoder = BaseHanDecoder::new();
+        decoder.update(&encoded).unwrap();
+        decoder.finish().unwrap();
+    });
+    assert_eq!(counter.spans.load(Ordering::SeqCst), 4);
+    assert_eq!(counter.events.load(Ordering::SeqCst), 4);
+
+    let counter = Arc::new(Counter::default());
+    tracing::subscriber::with_default(CountingSubscriber(counter.clone()), || {
+        assert!(BaseHanDecoder::new().update_str("\u{ffff}").is_err());
+    });
+    assert_eq!(counter.events.load(Ordering::SeqCst), 1); // The error
+}
@@ -0,0 +1,128 @@
+use basehan::v1;
+use basehan::v2::{BaseHanDecoder, BaseHanEncoder, BaseHanError};
+use proptest::prelude::*;
+
+#[test]
+fn round_trip_every_length_mod_7() {
+    // Cover every remaining bits count of the end marker, twice
+    for len in 0..=14 {
+        let data: Vec<u8> = (0..len).map(|i| (i * 53 + 7) as u8 | 0x80).collect();
+        let encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        let mut decoder = BaseHanDecoder::new();
+        assert_eq!(decoder.update_str(&encoded).unwrap(), data, "len {}", len);
+        if len > 0 {
+            assert_eq!(decoder.finish(), Ok(None), "len {}", len);
+        }
+    }
+}
+
+#[test]
+fn denser_than_v1() {
+    let data = [0xffu8; 1400];
+    let v1_len = v1::BaseHanEncoder::encode_bytes_to_string(&data)
+        .unwrap()
+        .chars()
+        .count();
+    let v2_len = BaseHanEncoder::encode_bytes_to_string(&data)
+        .unwrap()
+        .chars()
+        .count();
+    assert_eq!(v2_len, 800 + 1);
+    assert!(v2_len < v1_len);
+}
+
+#[test]
+fn versions_do_not_mix() {
+    let v1_encoded = v1::BaseHanEncoder::encode_bytes_to_string(b"\x00\x00 v1").unwrap();
+    assert!(matches!(
+        BaseHanDecoder::decode_str_to_bytes(&v1_encoded),
+        Err(BaseHanError::CodeOutOfRange { pos: 0, valid_min: 0x5000, valid_max: 0x900d, .. })
+    ));
+    let v2_encoded = BaseHanEncoder::encode_bytes_to_string(b"v2").unwrap();
+    let mut decoder = v1::BaseHanDecoder::new();
+    assert!(decoder.update_str(&v2_encoded).is_err() || decoder.finish().is_err());
+}
+
+#[test]
+fn truncated_stream() {
+    let encoded: Vec<char> = BaseHanEncoder::encode_bytes_to_string(b"abc")
+        .unwrap()
+        .chars()
+        .collect();
+    let mut decoder = BaseHanDecoder::new();
+    decoder.update(&encoded[..encoded.len() - 1]).unwrap();
+    assert!(!decoder.is_finished());
+    assert_eq!(decoder.finish(), Err(BaseHanError::TruncatedStream));
+
+    let truncated = String::from_iter(&encoded[..encoded.len() - 1]);
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&truncated), Err(BaseHanError::TruncatedStream));
+    let trailing = String::from_iter(&encoded) + "\u{5000}";
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes(&trailing), Err(BaseHanError::EndOfFile));
+    assert_eq!(BaseHanDecoder::decode_str_to_bytes("").unwrap(), b"");
+}
+
+proptest! {
+    #[test]
+    fn round_trip(data in proptest::collection::vec(any::<u8>(), 0..1024), chunk_size in 1usize..64) {
+        let mut encoder = BaseHanEncoder::new();
+        let mut encoded = Vec::new();
+        for chunk in data.chunks(chunk_size) {
+            encoded.extend(encoder.update(chunk).unwrap());
+        }
+        encoded.extend(encoder.finish().unwrap_or_default());
+        let mut decoder = BaseHanDecoder::new();
+        let mut decoded = Vec::new();
+        for chunk in encoded.chunks(chunk_size) {
+            decoded.extend(decoder.update(chunk).unwrap());
+        }
+        prop_assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+fn auto_detect_routes_by_prefix() {
+    let data = b"auto detected";
+    let v1_prefixed = v1::BaseHanEncoder::encode_bytes_to_prefixed_string(data).unwrap();
+    let v2_prefixed = BaseHanEncoder::encode_bytes_to_prefixed_string(data).unwrap();
+    let unprefixed = v1::BaseHanEncoder::encode_bytes_to_string(data).unwrap();
+    assert!(v1_prefixed.starts_with(v1::VERSION_PREFIX));
+    assert!(v2_prefixed.starts_with(basehan::v2::VERSION_PREFIX));
+    for encoded in [&v1_prefixed, &v2_prefixed, &unprefixed] {
+        assert_eq!(v1::BaseHanDecoder::auto_detect(encoded).unwrap(), data);
+    }
+    assert_eq!(
+        v1::BaseHanDecoder::auto_detect(
+            &BaseHanEncoder::encode_bytes_to_prefixed_string(b"").unwrap()
+        ),
+        Ok(vec![])
+    );
+    assert_eq!(
+        v1::BaseHanDecoder::auto_detect("\u{9f0f}"),
+        Err(BaseHanError::InvalidCode(0x9f0f, 0))
+    );
+    // Decoders without auto detection reject the prefix
+    assert!(v1::BaseHanDecoder::decode_str_to_bytes(&v1_prefixed).is_err());
+    assert!(basehan::v2::BaseHanDecoder::decode_str_to_bytes(&v2_prefixed).is_err());
+}
+
+#[test]
+fn detect_version_from_the_chars() {
+    use basehan::detect_version;
+
+    for len in [1, 2, 13, 14, 100] {
+        let data: Vec<u8> = (0..len).map(|i| (i * 37) as u8 | 0x80).collect();
+        let v1_encoded = v1::BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        let v2_encoded = BaseHanEncoder::encode_bytes_to_string(&data).unwrap();
+        assert_eq!(detect_version(&v1_encoded), Some(1), "{}", v1_encoded);
+        assert_eq!(detect_version(&v2_encoded), Some(2), "{}", v2_encoded);
+        assert_eq!(detect_version(&v1::BaseHanEncoder::encode_bytes_to_prefixed_string(&data).unwrap()), Some(1));
+        assert_eq!(detect_version(&BaseHanEncoder::encode_bytes_to_prefixed_string(&data).unwrap()), Some(2));
+    }
+    assert_eq!(detect_version("\u{6e01}"), Some(1)); // Empty v1 message
+    assert_eq!(detect_version(""), None);
+    assert_eq!(detect_version("\u{5000}\u{6000}"), None); // Truncated, could be either
+    assert_eq!(detect_version("\u{7000}\u{5000}"), None); // Chars after a v1 terminator
+    assert_eq!(detect_version("\u{6e01}\u{4e00}"), None);
+    assert_eq!(detect_version("\u{6e01} \u{3000}"), Some(1)); // Only whitespace and padding
+    assert_eq!(detect_version("plain text"), None);
+}
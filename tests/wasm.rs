@@ -0,0 +1,25 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use basehan::wasm::{decode, encode};
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn round_trip() {
+    for len in 0..=26 {
+        let data: Vec<u8> = (0..len).map(|i| i as u8 ^ 0x5a).collect();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+    }
+}
+
+#[wasm_bindgen_test]
+fn decode_rejects_invalid_input() {
+    assert!(decode("abc").is_err());
+}
+
+#[wasm_bindgen_test]
+fn decode_rejects_incomplete_streams() {
+    let encoded = encode(b"complete");
+    let truncated: String = encoded.chars().take(encoded.chars().count() - 1).collect();
+    assert!(decode(&truncated).is_err());
+    assert!(decode(&(encoded + "\u{4e00}")).is_err());
+}